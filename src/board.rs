@@ -182,11 +182,9 @@ impl Board {
                 for (nx, ny) in self.neighbors(cx, cy) {
                     let ni = self.idx(nx, ny);
                     match self.get(nx, ny) {
-                        None => {
-                            if !liberty_visited[ni] {
-                                liberty_visited[ni] = true;
-                                liberties += 1;
-                            }
+                        None if !liberty_visited[ni] => {
+                            liberty_visited[ni] = true;
+                            liberties += 1;
                         }
                         Some(c) if c == color && !visited[ni] => stack.push((nx, ny)),
                         _ => {}
@@ -0,0 +1,242 @@
+//! Tunable search parameters.
+//!
+//! [`SearchConfig`] holds the MCTS and playout parameters that used to be
+//! compile-time constants, so that they can be tuned without rebuilding: from
+//! a config file, from `--param name=value` on the command line, or at runtime
+//! with the GTP `michi-param` command. The defaults are the values in
+//! [`crate::constants`].
+//!
+//! A config file has one `name = value` per line; blank lines and `#`
+//! comments are ignored. `prior_cfg` takes its three values separated by
+//! commas.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::constants::{
+    EXPAND_VISITS, FASTPLAY5_THRES, FASTPLAY20_THRES, PRIOR_CAPTURE_MANY, PRIOR_CAPTURE_ONE,
+    PRIOR_CFG, PRIOR_EMPTYAREA, PRIOR_EVEN, PRIOR_LARGEPATTERN, PRIOR_PAT3, PRIOR_SELFATARI,
+    PROB_HEURISTIC_CAPTURE, PROB_HEURISTIC_PAT3, PROB_RSAREJECT, PROB_SSAREJECT, RAVE_EQUIV,
+    RESIGN_THRES,
+};
+
+/// Search and playout parameters. See the constants of the same names for
+/// what each one does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchConfig {
+    pub rave_equiv: usize,
+    pub expand_visits: u32,
+    pub resign_thres: f64,
+    pub fastplay20_thres: f64,
+    pub fastplay5_thres: f64,
+    pub prior_even: u32,
+    pub prior_selfatari: u32,
+    pub prior_capture_one: u32,
+    pub prior_capture_many: u32,
+    pub prior_pat3: u32,
+    pub prior_largepattern: u32,
+    pub prior_cfg: [u32; 3],
+    pub prior_emptyarea: u32,
+    pub prob_heuristic_capture: f64,
+    pub prob_heuristic_pat3: f64,
+    pub prob_ssareject: f64,
+    pub prob_rsareject: f64,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            rave_equiv: RAVE_EQUIV,
+            expand_visits: EXPAND_VISITS,
+            resign_thres: RESIGN_THRES,
+            fastplay20_thres: FASTPLAY20_THRES,
+            fastplay5_thres: FASTPLAY5_THRES,
+            prior_even: PRIOR_EVEN,
+            prior_selfatari: PRIOR_SELFATARI,
+            prior_capture_one: PRIOR_CAPTURE_ONE,
+            prior_capture_many: PRIOR_CAPTURE_MANY,
+            prior_pat3: PRIOR_PAT3,
+            prior_largepattern: PRIOR_LARGEPATTERN,
+            prior_cfg: PRIOR_CFG,
+            prior_emptyarea: PRIOR_EMPTYAREA,
+            prob_heuristic_capture: PROB_HEURISTIC_CAPTURE,
+            prob_heuristic_pat3: PROB_HEURISTIC_PAT3,
+            prob_ssareject: PROB_SSAREJECT,
+            prob_rsareject: PROB_RSAREJECT,
+        }
+    }
+}
+
+impl SearchConfig {
+    /// Names of all parameters, in display order.
+    pub const NAMES: [&'static str; 17] = [
+        "rave_equiv",
+        "expand_visits",
+        "resign_thres",
+        "fastplay20_thres",
+        "fastplay5_thres",
+        "prior_even",
+        "prior_selfatari",
+        "prior_capture_one",
+        "prior_capture_many",
+        "prior_pat3",
+        "prior_largepattern",
+        "prior_cfg",
+        "prior_emptyarea",
+        "prob_heuristic_capture",
+        "prob_heuristic_pat3",
+        "prob_ssareject",
+        "prob_rsareject",
+    ];
+
+    /// The value of a parameter, formatted as `set` accepts it.
+    pub fn get(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "rave_equiv" => self.rave_equiv.to_string(),
+            "expand_visits" => self.expand_visits.to_string(),
+            "resign_thres" => self.resign_thres.to_string(),
+            "fastplay20_thres" => self.fastplay20_thres.to_string(),
+            "fastplay5_thres" => self.fastplay5_thres.to_string(),
+            "prior_even" => self.prior_even.to_string(),
+            "prior_selfatari" => self.prior_selfatari.to_string(),
+            "prior_capture_one" => self.prior_capture_one.to_string(),
+            "prior_capture_many" => self.prior_capture_many.to_string(),
+            "prior_pat3" => self.prior_pat3.to_string(),
+            "prior_largepattern" => self.prior_largepattern.to_string(),
+            "prior_cfg" => {
+                let [a, b, c] = self.prior_cfg;
+                format!("{a},{b},{c}")
+            }
+            "prior_emptyarea" => self.prior_emptyarea.to_string(),
+            "prob_heuristic_capture" => self.prob_heuristic_capture.to_string(),
+            "prob_heuristic_pat3" => self.prob_heuristic_pat3.to_string(),
+            "prob_ssareject" => self.prob_ssareject.to_string(),
+            "prob_rsareject" => self.prob_rsareject.to_string(),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Set a parameter from its text value.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "rave_equiv" => self.rave_equiv = parse(name, value)?,
+            "expand_visits" => self.expand_visits = parse(name, value)?,
+            "resign_thres" => self.resign_thres = parse_fraction(name, value)?,
+            "fastplay20_thres" => self.fastplay20_thres = parse_fraction(name, value)?,
+            "fastplay5_thres" => self.fastplay5_thres = parse_fraction(name, value)?,
+            "prior_even" => self.prior_even = parse(name, value)?,
+            "prior_selfatari" => self.prior_selfatari = parse(name, value)?,
+            "prior_capture_one" => self.prior_capture_one = parse(name, value)?,
+            "prior_capture_many" => self.prior_capture_many = parse(name, value)?,
+            "prior_pat3" => self.prior_pat3 = parse(name, value)?,
+            "prior_largepattern" => self.prior_largepattern = parse(name, value)?,
+            "prior_cfg" => {
+                let values = value
+                    .split(',')
+                    .map(|v| parse(name, v.trim()))
+                    .collect::<Result<Vec<u32>>>()?;
+                self.prior_cfg = values
+                    .try_into()
+                    .map_err(|_| anyhow!("prior_cfg takes three values, e.g. 24,22,8"))?;
+            }
+            "prior_emptyarea" => self.prior_emptyarea = parse(name, value)?,
+            "prob_heuristic_capture" => self.prob_heuristic_capture = parse_fraction(name, value)?,
+            "prob_heuristic_pat3" => self.prob_heuristic_pat3 = parse_fraction(name, value)?,
+            "prob_ssareject" => self.prob_ssareject = parse_fraction(name, value)?,
+            "prob_rsareject" => self.prob_rsareject = parse_fraction(name, value)?,
+            _ => bail!("unknown parameter {name}"),
+        }
+        Ok(())
+    }
+
+    /// Set a parameter from a `name=value` string, as given on the command line.
+    pub fn set_assignment(&mut self, assignment: &str) -> Result<()> {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected name=value, got {assignment}"))?;
+        self.set(name.trim(), value.trim())
+    }
+
+    /// Apply the `name = value` lines of a config file.
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read config file {}", path.display()))?;
+        self.parse_lines(&text)
+            .with_context(|| format!("in config file {}", path.display()))
+    }
+
+    /// Apply the `name = value` lines of a config file's contents.
+    pub fn parse_lines(&mut self, text: &str) -> Result<()> {
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            self.set_assignment(line)
+                .with_context(|| format!("line {}", i + 1))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a parameter value.
+fn parse<T: FromStr>(name: &str, value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("invalid value for {name}: {value}"))
+}
+
+/// Parse a probability or winrate parameter, which must be in [0, 1].
+fn parse_fraction(name: &str, value: &str) -> Result<f64> {
+    let v: f64 = parse(name, value)?;
+    if !(0.0..=1.0).contains(&v) {
+        bail!("{name} must be between 0 and 1, got {value}");
+    }
+    Ok(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_and_set() {
+        let mut config = SearchConfig::default();
+        for name in SearchConfig::NAMES {
+            let value = config.get(name).unwrap();
+            config.set(name, &value).unwrap();
+        }
+        assert_eq!(config, SearchConfig::default());
+
+        config.set("rave_equiv", "2000").unwrap();
+        assert_eq!(config.rave_equiv, 2000);
+        config.set("prior_cfg", "30, 20, 10").unwrap();
+        assert_eq!(config.prior_cfg, [30, 20, 10]);
+        assert_eq!(config.get("prior_cfg").unwrap(), "30,20,10");
+
+        assert!(config.set("no_such_param", "1").is_err());
+        assert!(config.set("expand_visits", "-1").is_err());
+        assert!(config.set("prob_ssareject", "1.5").is_err());
+        assert!(config.set("prior_cfg", "1,2").is_err());
+        assert_eq!(config.get("no_such_param"), None);
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        let mut config = SearchConfig::default();
+        config
+            .parse_lines("# sweep 3\nexpand_visits = 4\n\nresign_thres=0.1  # lower\n")
+            .unwrap();
+        assert_eq!(config.expand_visits, 4);
+        assert_eq!(config.resign_thres, 0.1);
+
+        let err = config.parse_lines("prior_pat3 = 5\nbogus\n").unwrap_err();
+        assert!(format!("{err:#}").contains("line 2"));
+
+        config.set_assignment("prior_even=20").unwrap();
+        assert_eq!(config.prior_even, 20);
+    }
+}
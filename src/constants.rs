@@ -2,51 +2,54 @@
 //!
 //! This module contains all the configuration constants for the Go engine.
 //! The board uses a 1D array representation with padding for boundary detection.
+//! The search parameters are the defaults of [`SearchConfig`], which can be
+//! changed without recompiling.
 //!
-//! # Board Size Configuration
+//! # Board Size
 //!
-//! The board size is controlled by Cargo features:
-//! - `board9x9` (default): 9x9 board
-//! - `board13x13`: 13x13 board
+//! The board size is chosen at runtime (see [`Position::with_size`]). The 1D
+//! array always has room for the largest supported board, and rows are laid
+//! out from the bottom edge up with a fixed stride, so a [`Point`] such as
+//! `D4` has the same index on every board size.
 //!
-//! To compile for a specific board size:
-//! ```sh
-//! cargo build                           # 9x9 (default)
-//! cargo build --no-default-features --features board13x13  # 13x13
-//! ```
+//! [`Position::with_size`]: crate::position::Position::with_size
+//! [`Point`]: crate::position::Point
+//! [`SearchConfig`]: crate::config::SearchConfig
 
 // =============================================================================
 // Board Geometry
 // =============================================================================
 
-/// Board size (NxN). Standard Go sizes are 9, 13, or 19.
-#[cfg(feature = "board9x9")]
-pub const N: usize = 9;
+/// Smallest supported board size.
+pub const MIN_N: usize = 2;
 
-#[cfg(feature = "board13x13")]
-pub const N: usize = 13;
+/// Largest supported board size.
+pub const MAX_N: usize = 19;
 
-// Compile-time check: exactly one board size feature must be enabled
-#[cfg(all(feature = "board9x9", feature = "board13x13"))]
-compile_error!("Cannot enable both 'board9x9' and 'board13x13' features at the same time");
+/// Board size used by [`Position::new`](crate::position::Position::new).
+pub const DEFAULT_N: usize = 9;
 
-#[cfg(not(any(feature = "board9x9", feature = "board13x13")))]
-compile_error!("Must enable exactly one board size feature: 'board9x9' or 'board13x13'");
+/// Row stride of the 1D board array: `MAX_N` points plus one padding column,
+/// which serves as both the left and the right edge.
+pub const W: usize = MAX_N + 1;
 
-/// Board width including left padding (N + 2 for padding on both sides).
-pub const W: usize = N + 2;
+/// Total board array size including all padding (rows 0 and `MAX_N + 1` are padding).
+pub const BOARDSIZE: usize = (MAX_N + 2) * W + 1;
 
-/// Total board array size including all padding. Matches C layout for compatibility.
-pub const BOARDSIZE: usize = (N + 1) * W + 1;
+/// First valid board index (A1, skipping the bottom padding row and left padding).
+pub const BOARD_IMIN: usize = W + 1;
 
-/// First valid board index (skips top and left padding).
-pub const BOARD_IMIN: usize = N + 1;
+/// One past the last valid board index for an `n`x`n` board.
+#[inline]
+pub const fn board_imax(n: usize) -> usize {
+    n * W + n + 1
+}
 
-/// Last valid board index (before bottom padding).
-pub const BOARD_IMAX: usize = BOARDSIZE - N - 1;
-
-/// Maximum game length (3 times board area to allow for captures and replays).
-pub const MAX_GAME_LEN: usize = N * N * 3;
+/// Maximum game length for an `n`x`n` board (3 times board area to allow for captures and replays).
+#[inline]
+pub const fn max_game_len(n: usize) -> usize {
+    n * n * 3
+}
 
 // =============================================================================
 // Special Move Values
@@ -71,18 +74,65 @@ pub const RAVE_EQUIV: usize = 3500;
 /// Minimum visits before expanding a node.
 pub const EXPAND_VISITS: u32 = 8;
 
+/// Default memory cap of the search tree, in MiB.
+pub const TREE_MEMORY_MB: usize = 1024;
+
 /// Progress report period (number of simulations between reports).
 pub const REPORT_PERIOD: usize = 200;
 
+/// Most moves in the principal variation of a search result.
+pub const PV_LEN: usize = 10;
+
 /// Winrate threshold below which the engine resigns.
 pub const RESIGN_THRES: f64 = 0.2;
 
+/// Ownership threshold for final stone status: a group its own color owns in
+/// more than this fraction of playouts (net) is alive, one the opponent owns is
+/// dead, anything in between is reported as seki.
+pub const STATUS_THRES: f64 = 0.3;
+
 /// Fast-play threshold at 20% of simulations.
 pub const FASTPLAY20_THRES: f64 = 0.8;
 
 /// Fast-play threshold at 5% of simulations.
 pub const FASTPLAY5_THRES: f64 = 0.95;
 
+// =============================================================================
+// Time Management
+// =============================================================================
+
+/// Safety margin (seconds) kept off every move's time budget for lag.
+pub const TIME_MARGIN: f64 = 0.5;
+
+/// Longest time budget (seconds) for a move, whatever the clock says.
+pub const MAX_MOVE_TIME: f64 = 24.0 * 3600.0;
+
+/// Fewest moves main time is assumed to have to last for.
+pub const MIN_MOVES_LEFT: usize = 15;
+
+/// How far past its soft budget a move may search while the best move is unclear.
+pub const UNCLEAR_TIME_FACTOR: f64 = 3.0;
+
+/// The best move is clear once it has this many times the visits of the second best.
+pub const UNCLEAR_LEAD: f64 = 1.5;
+
+// =============================================================================
+// Pondering
+// =============================================================================
+
+/// Simulations per pondering batch (checks for a new command come in between).
+pub const PONDER_BATCH: usize = 50;
+
+/// Pondering stops once the root has this many times the simulations per move.
+pub const PONDER_SIMS_FACTOR: usize = 4;
+
+// =============================================================================
+// Analysis
+// =============================================================================
+
+/// Default time between `lz-analyze` / `kata-analyze` reports, in centiseconds.
+pub const ANALYZE_INTERVAL: u64 = 100;
+
 // =============================================================================
 // Prior Values (for MCTS node initialization)
 // =============================================================================
@@ -134,14 +184,14 @@ pub const PROB_RSAREJECT: f64 = 0.5;
 /// Offsets to neighboring points in the 1D board array.
 /// Order: North, East, South, West, NE, SE, SW, NW
 pub const DELTA: [isize; 8] = [
-    -(N as isize) - 1, // North (up one row)
+    W as isize,        // North (up one row)
     1,                 // East (right one column)
-    (N as isize) + 1,  // South (down one row)
+    -(W as isize),     // South (down one row)
     -1,                // West (left one column)
-    -(N as isize),     // NE (diagonal)
-    W as isize,        // SE (diagonal)
-    N as isize,        // SW (diagonal)
-    -(W as isize),     // NW (diagonal)
+    W as isize + 1,    // NE (diagonal)
+    -(W as isize) + 1, // SE (diagonal)
+    -(W as isize) - 1, // SW (diagonal)
+    W as isize - 1,    // NW (diagonal)
 ];
 
 // =============================================================================
@@ -12,11 +12,45 @@
 //! - `list_commands` - List all supported commands
 //! - `known_command <cmd>` - Check if a command is supported
 //! - `quit` - Exit the program
-//! - `boardsize <size>` - Set board size (only 13 is supported currently)
+//! - `boardsize <size>` - Set board size (2 to 19) and clear the board
 //! - `clear_board` - Reset the board to empty
-//! - `komi <value>` - Set komi (only 7.5 is supported currently)
-//! - `play <color> <vertex>` - Play a move
+//! - `komi <value>` - Set komi
+//! - `fixed_handicap <n>` - Place `n` handicap stones on the standard star points
+//! - `place_free_handicap <n>` - Place `n` handicap stones where the engine chooses
+//! - `set_free_handicap <vertex...>` - Place handicap stones chosen by the controller
+//! - `play <color> <vertex>` - Play a move (out of turn if needed)
 //! - `genmove <color>` - Generate and play a move for the given color
+//! - `undo` - Take back the last move played with `play` or `genmove`
+//! - `loadsgf <file> [move]` - Load a game record, up to (not including) `move`
+//! - `printsgf [file]` - Output the game as SGF, or write it to `file`
+//! - `final_score` - Score the game, estimating dead stones with playouts
+//! - `final_status_list <alive|dead|seki>` - List the groups with that status,
+//!   one group per line
+//! - `kgs-rules <chinese|japanese|aga|new_zealand|tromp-taylor>` - Set the rule set
+//! - `time_settings <main> <byo_yomi_time> <byo_yomi_stones>` - Set Canadian
+//!   (or absolute) time controls
+//! - `kgs-time_settings <none|absolute|byoyomi|canadian> ...` - Set time controls
+//! - `time_left <color> <time> <stones>` - Update a player's clock
+//! - `michi-superko [off|positional|situational]` - Show or set the superko rule
+//!   (positional by default)
+//! - `michi-param [name [value]]` - List the search parameters, or show or set one
+//! - `michi-seed [seed]` - Show the random seed, or restart the random number
+//!   generator from `seed` (with a fresh tree) to replay a session
+//! - `lz-analyze [color] [interval]` - Search until the next command, reporting
+//!   the candidate moves every `interval` centiseconds in Leela Zero's format
+//!   (on one thread, like pondering)
+//! - `kata-analyze [color] [interval] [ownership true]` - The same in KataGo's
+//!   format, optionally with the ownership of every point
+//! - `debug <setpos|fix_atari|match_pat> ...` - michi-c's debug commands, so
+//!   that its `.tst` regression suites run unmodified
+//! - `gogui-interrupt` - Advertises that a `# interrupt` line stops `genmove`
+//! - `gogui-rules_game_id`, `gogui-rules_board_size`, `gogui-rules_side_to_move`,
+//!   `gogui-rules_legal_moves`, `gogui-rules_final_result` - GoGui rules queries
+//!
+//! Rules default to Chinese (area scoring, no suicide, positional superko).
+//!
+//! When a command names the color that is not to move, an implicit pass is
+//! inserted for the other color so the engine's board matches the controller.
 //!
 //! ## Example
 //!
@@ -25,47 +59,149 @@
 //! let mut engine = GtpEngine::new();
 //! engine.run();
 //! ```
+//!
+//! Any reader and writer can stand in for stdin and stdout, for instance to
+//! embed the engine or to drive it from tests:
+//!
+//! ```
+//! use std::io::Cursor;
+//! use michi_rust::gtp::GtpEngine;
+//!
+//! let mut output = Vec::new();
+//! let mut engine = GtpEngine::with_simulations(10).without_diagnostics();
+//! engine.run_with(Cursor::new("1 name\n2 quit\n"), &mut output).unwrap();
+//! assert_eq!(String::from_utf8(output).unwrap(), "=1 michi-rust\n\n=2 \n\n");
+//! ```
 
-use std::io::{self, BufRead, Write};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::mpsc::{self, TryRecvError};
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
+use fastrand::Rng;
 
-use crate::constants::{BOARDSIZE, N, N_SIMS, PASS_MOVE, RESIGN_MOVE, RESIGN_THRES};
-use crate::mcts::{TreeNode, tree_search_with_display};
+use crate::config::SearchConfig;
+use crate::constants::{
+    ANALYZE_INTERVAL, BOARD_IMIN, BOARDSIZE, EMPTY, MAX_N, MIN_N, N_SIMS, PASS_MOVE, PONDER_BATCH,
+    PONDER_SIMS_FACTOR, RESIGN_MOVE, STONE_BLACK, STONE_WHITE, TREE_MEMORY_MB, W,
+};
+use crate::handicap::{fixed_handicap_points, free_handicap_points, place_handicap};
+use crate::mcts::{
+    CancelToken, GroupStatus, LogObserver, NullObserver, SearchLimits, SearchObserver,
+    SearchResult, Tree, estimate_ownership, expand_root_with, group_statuses, tree_search,
+    tree_search_parallel, tree_search_with_owner,
+};
+use crate::patterns::matching_pattern_ids;
+use crate::playout::{final_score, score};
 use crate::position::{
-    Position, format_position_with_owner, parse_coord, pass_move, play_move, str_coord,
+    MoveError, Point, Position, compute_block, fix_atari_ext, format_position_with_owner,
+    parse_coord, pass_move, play_move, play_move_for, set_to_play, str_coord,
 };
+use crate::rules::{Rules, Superko};
+use crate::sgf::{load_positions, parse_sgf, write_sgf};
+use crate::timecontrol::{TimeManager, TimeSettings};
 
 /// The list of known GTP commands.
 const KNOWN_COMMANDS: &[&str] = &[
     "boardsize",
     "clear_board",
     "cputime",
+    "debug",
+    "final_score",
+    "final_status_list",
+    "fixed_handicap",
     "genmove",
+    "gogui-interrupt",
+    "gogui-rules_board_size",
+    "gogui-rules_final_result",
+    "gogui-rules_game_id",
+    "gogui-rules_legal_moves",
+    "gogui-rules_side_to_move",
     "help",
+    "kata-analyze",
+    "kgs-rules",
+    "kgs-time_settings",
     "known_command",
     "komi",
     "list_commands",
+    "loadsgf",
+    "lz-analyze",
+    "michi-param",
+    "michi-seed",
+    "michi-superko",
     "name",
+    "place_free_handicap",
     "play",
+    "printsgf",
     "protocol_version",
     "quit",
+    "set_free_handicap",
     "showboard",
+    "time_left",
+    "time_settings",
+    "undo",
     "version",
 ];
 
+/// An `lz-analyze` or `kata-analyze` session, running until the next command.
+struct Analysis {
+    /// Report in KataGo's format rather than Leela Zero's
+    kata: bool,
+    /// Append the ownership of every point to each report
+    ownership: bool,
+    /// Time between reports
+    interval: Duration,
+    /// When the last report was sent
+    last_report: Instant,
+    /// Playouts counted in the owner map
+    playouts: usize,
+}
+
 /// GTP engine state.
 pub struct GtpEngine {
     /// Current game position
     pos: Position,
-    /// MCTS tree (recreated after each move)
-    tree: Option<TreeNode>,
+    /// MCTS tree, kept between moves so that search effort is reused
+    tree: Option<Tree>,
+    /// Positions before each move of the game, for `undo` and superko
+    history: Vec<Position>,
+    /// Superko keys of `history` and `pos`, each with the number of
+    /// positions it stands for so that `undo` can drop one
+    seen: HashMap<u64, usize>,
     /// Number of simulations for MCTS search
     n_sims: usize,
+    /// Number of search threads
+    threads: usize,
+    /// Search and playout parameters
+    config: SearchConfig,
+    /// Time settings and both players' clocks
+    time: TimeManager,
+    /// Whether to search between commands
+    ponder: bool,
+    /// Analysis in progress, if any
+    analysis: Option<Analysis>,
+    /// Node cap of the search tree
+    max_nodes: usize,
     /// Owner map for territory display
     owner_map: Vec<i32>,
     /// Start time for cputime command
     start_time: std::time::Instant,
+    /// Stops the `genmove` search, keeping the best move found so far
+    cancel: CancelToken,
+    /// Seed of `rng`, reported by `michi-seed` so that a game can be replayed
+    seed: u64,
+    /// Seeds the trees' generators and draws the final-score playouts
+    rng: Rng,
+    /// Where the board, the prompt and the search progress go (stderr by
+    /// default), or `None` to leave them out
+    diagnostics: Option<Box<dyn Write + Send>>,
+}
+
+impl Default for GtpEngine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GtpEngine {
@@ -76,21 +212,117 @@ impl GtpEngine {
 
     /// Create a new GTP engine with a specified number of simulations per move.
     pub fn with_simulations(n_sims: usize) -> Self {
-        let pos = Position::new();
-        let tree = Some(TreeNode::new(&pos));
-        Self {
-            pos,
-            tree,
+        let seed = fastrand::u64(..);
+        let mut engine = Self {
+            pos: Position::new(),
+            tree: None,
+            history: Vec::new(),
+            seen: HashMap::new(),
             n_sims,
+            threads: 1,
+            config: SearchConfig::default(),
+            time: TimeManager::default(),
+            ponder: false,
+            analysis: None,
+            max_nodes: Tree::max_nodes_for_memory(TREE_MEMORY_MB << 20),
             owner_map: vec![0i32; BOARDSIZE],
             start_time: std::time::Instant::now(),
+            cancel: CancelToken::new(),
+            seed,
+            rng: Rng::with_seed(seed),
+            diagnostics: Some(Box::new(io::stderr())),
+        };
+        engine.rebuild_seen();
+        engine.tree = Some(engine.new_tree());
+        engine
+    }
+
+    /// Search with `threads` threads (root parallelism). One thread, the
+    /// default, gives the deterministic single-threaded search.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Search with the parameters in `config` instead of the defaults.
+    pub fn with_config(mut self, config: SearchConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Keep searching the current tree while waiting for commands in `run`.
+    pub fn with_ponder(mut self, ponder: bool) -> Self {
+        self.ponder = ponder;
+        self
+    }
+
+    /// Draw all random choices from a generator seeded with `seed`, so that
+    /// a session can be replayed exactly (with one search thread, or with
+    /// several and no time limit).
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.reseed(seed);
+        self
+    }
+
+    /// Restart the random number generator from `seed`, with a fresh tree.
+    fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = Rng::with_seed(seed);
+        self.tree = Some(self.new_tree());
+    }
+
+    /// Write the diagnostics (the board after each command, the prompt and
+    /// the search progress) to `sink` instead of stderr.
+    pub fn with_diagnostics(mut self, sink: impl Write + Send + 'static) -> Self {
+        self.diagnostics = Some(Box::new(sink));
+        self
+    }
+
+    /// Leave out the diagnostics altogether.
+    pub fn without_diagnostics(mut self) -> Self {
+        self.diagnostics = None;
+        self
+    }
+
+    /// Cap the search tree at `mb` MiB of nodes.
+    pub fn with_tree_memory(mut self, mb: usize) -> Self {
+        self.max_nodes = Tree::max_nodes_for_memory(mb << 20);
+        self.tree = Some(self.new_tree());
+        self
+    }
+
+    /// A handle that stops the current (or next) `genmove` search from
+    /// another thread; the engine then plays the best move found so far.
+    pub fn cancel_handle(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    /// A fresh search tree at the current position.
+    fn new_tree(&mut self) -> Tree {
+        let pos = self.pos.clone();
+        self.new_tree_at(&pos)
+    }
+
+    /// A fresh search tree at `pos`, with a generator forked from the engine's.
+    fn new_tree_at(&mut self, pos: &Position) -> Tree {
+        let mut tree = Tree::with_max_nodes(pos, self.max_nodes);
+        *tree.rng_mut() = self.rng.fork();
+        tree
+    }
+
+    /// Write `text` to the diagnostics sink, if any. Errors are ignored:
+    /// diagnostics must not disturb the protocol.
+    fn diagnose(&mut self, text: &str) {
+        if let Some(out) = self.diagnostics.as_mut() {
+            let _ = out.write_all(text.as_bytes());
+            let _ = out.flush();
         }
     }
 
-    /// Print the board state to stderr with owner map.
-    fn print_board(&self) {
+    /// Write the board state with owner map to the diagnostics sink.
+    fn print_board(&mut self) {
         let board_str = format_position_with_owner(&self.pos, Some(&self.owner_map), self.n_sims);
-        eprint!("{}", board_str);
+        self.diagnose(&board_str);
     }
 
     /// Get the prompt indicator based on whose turn it is.
@@ -105,82 +337,523 @@ impl GtpEngine {
 
     /// Run the GTP command loop, reading from stdin and writing to stdout.
     pub fn run(&mut self) -> Result<()> {
-        let stdin = io::stdin();
-        let mut stdout = io::stdout();
-        let mut stderr = io::stderr();
+        self.run_with(BufReader::new(io::stdin()), io::stdout())
+    }
 
-        for line in stdin.lock().lines() {
-            let line = line?;
+    /// Run the GTP command loop, reading commands from `input` and writing
+    /// responses to `output`, until `quit` or the end of the input.
+    ///
+    /// The input is read on a separate thread so that work between commands
+    /// (pondering, `lz-analyze` / `kata-analyze`) can go on in small batches
+    /// while no command is waiting; one arriving stops it after at most
+    /// `PONDER_BATCH` simulations.
+    ///
+    /// A `# interrupt` line (GoGui's interrupt protocol) is not a command:
+    /// it stops a running `genmove` search, which then plays the best move
+    /// found so far.
+    pub fn run_with<R, W>(&mut self, input: R, mut output: W) -> Result<()>
+    where
+        R: BufRead + Send + 'static,
+        W: Write,
+    {
+        let (tx, rx) = mpsc::channel();
+        let cancel = self.cancel.clone();
+        std::thread::spawn(move || {
+            for line in input.lines() {
+                if line.as_ref().is_ok_and(|l| l.trim() == "# interrupt") {
+                    cancel.cancel();
+                    continue;
+                }
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        loop {
+            let line = match rx.try_recv() {
+                Ok(line) => line,
+                Err(TryRecvError::Empty) => {
+                    if self.analysis.is_some() {
+                        if let Some(info) = self.analyze_step() {
+                            writeln!(output, "{info}")?;
+                            output.flush()?;
+                        }
+                        continue;
+                    }
+                    if self.ponder && self.ponder_step() {
+                        continue;
+                    }
+                    // Nothing left to do: wait for the next command
+                    match rx.recv() {
+                        Ok(line) => line,
+                        Err(_) => break,
+                    }
+                }
+                Err(TryRecvError::Disconnected) => break,
+            };
+            self.stop_analysis(&mut output)?;
+            if !self.handle_line(&line?, &mut output)? {
+                break;
+            }
+        }
+        self.stop_analysis(&mut output)?;
+
+        Ok(())
+    }
 
-            // Skip empty lines and comments
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('#') {
-                continue;
+    /// Run one batch of pondering simulations on the current tree.
+    ///
+    /// The tree is rooted at the current position, so after `genmove` this
+    /// searches the opponent's replies; tree reuse keeps the work for the
+    /// next move. Returns false once there is nothing (more) to ponder.
+    fn ponder_step(&mut self) -> bool {
+        let limit = (self.n_sims * PONDER_SIMS_FACTOR).min(u32::MAX as usize) as u32;
+        match self.tree.as_mut() {
+            Some(tree) if tree.root().v < limit => {
+                tree_search(tree, PONDER_BATCH, &self.config);
+                true
             }
+            _ => false,
+        }
+    }
+
+    /// Run one batch of analysis simulations, returning a report line once
+    /// the interval since the last one has passed.
+    ///
+    /// Like pondering, analysis runs on one thread whatever `threads` is:
+    /// the batches are kept short so that the next command stops them.
+    fn analyze_step(&mut self) -> Option<String> {
+        let tree = self.tree.as_mut()?;
+        let analysis = self.analysis.as_mut()?;
+        let result = tree_search_with_owner(tree, PONDER_BATCH, &self.config, &mut self.owner_map);
+        analysis.playouts += result.sims;
+        if analysis.last_report.elapsed() < analysis.interval {
+            return None;
+        }
+        analysis.last_report = Instant::now();
+        let info = self.analysis_info(&result);
+        (!info.is_empty()).then_some(info)
+    }
 
-            // Parse optional command ID
-            let (id, command_line) = Self::parse_id(line);
+    /// The analysis report: one `info move ...` entry per visited candidate,
+    /// most visited first, and the ownership if it was asked for.
+    ///
+    /// Winrates are for the side to move. The prior of a move is its share
+    /// of the prior wins of all the moves.
+    fn analysis_info(&self, result: &SearchResult) -> String {
+        let (Some(tree), Some(analysis)) = (&self.tree, &self.analysis) else {
+            return String::new();
+        };
+        let prior_total: u32 = result.candidates.iter().map(|c| c.prior_wins).sum();
+        let prior_total = f64::from(prior_total.max(1));
+
+        let mut entries = Vec::new();
+        for (order, c) in result.candidates.iter().enumerate() {
+            let Some(winrate) = c.winrate() else {
+                break;
+            };
+            let prior = f64::from(c.prior_wins) / prior_total;
+            let pv: Vec<String> = c.pv.iter().map(|&mv| str_coord(mv)).collect();
+            let (winrate, prior) = if analysis.kata {
+                (format!("{winrate:.6}"), format!("{prior:.6}"))
+            } else {
+                // Leela Zero gives both in hundredths of a percent
+                (
+                    format!("{:.0}", winrate * 10000.0),
+                    format!("{:.0}", prior * 10000.0),
+                )
+            };
+            entries.push(format!(
+                "info move {} visits {} winrate {winrate} prior {prior} order {order} pv {}",
+                str_coord(c.mv),
+                c.visits,
+                pv.join(" ")
+            ));
+        }
+        if entries.is_empty() {
+            return String::new();
+        }
 
-            // Parse command and arguments
-            let parts: Vec<&str> = command_line.split_whitespace().collect();
-            if parts.is_empty() {
-                continue;
+        if analysis.ownership {
+            // From the side to move, row by row from the top left
+            let pos = tree.pos();
+            let sign = if pos.is_black_to_play() { 1.0 } else { -1.0 };
+            let playouts = analysis.playouts.max(1) as f64;
+            let mut ownership = vec!["ownership".to_string()];
+            for row in (1..=pos.size).rev() {
+                for col in 1..=pos.size {
+                    let own = sign * f64::from(self.owner_map[row * W + col]) / playouts;
+                    ownership.push(format!("{own:.3}"));
+                }
             }
+            entries.push(ownership.join(" "));
+        }
+        entries.join(" ")
+    }
 
-            let command = parts[0].to_lowercase();
-            let args = &parts[1..];
+    /// End the analysis in progress, if any, closing its response.
+    fn stop_analysis(&mut self, output: &mut dyn Write) -> Result<()> {
+        if self.analysis.take().is_some() {
+            writeln!(output)?;
+            output.flush()?;
+        }
+        Ok(())
+    }
 
-            // Execute command
-            let response = self.execute(&command, args);
+    /// Parse the arguments of `lz-analyze` / `kata-analyze`: an optional
+    /// color, then the report interval in centiseconds, given alone or as
+    /// `interval <n>`, and `ownership <true|false>`.
+    ///
+    /// Returns the color, the interval and whether ownership was asked for.
+    fn parse_analyze_args(args: &[&str]) -> Option<(Option<bool>, u64, bool)> {
+        let mut color = None;
+        let mut interval = ANALYZE_INTERVAL;
+        let mut ownership = false;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.to_lowercase().as_str() {
+                "interval" => interval = args.next()?.parse().ok()?,
+                "ownership" => ownership = args.next()?.parse().ok()?,
+                a if color.is_none() && Self::parse_color(a).is_some() => {
+                    color = Self::parse_color(a);
+                }
+                a => interval = a.parse().ok()?,
+            }
+        }
+        Some((color, interval, ownership))
+    }
 
-            // Print board after command execution (to stderr, like michi-c)
-            self.print_board();
-            stderr.flush()?;
+    /// Expand the root of `tree`, dropping the moves that would repeat an
+    /// earlier position of the game.
+    fn expand_root(&self, tree: &mut Tree) {
+        expand_root_with(tree, &self.config, |p| !self.repeats(p));
+    }
 
-            // Format and send response
-            let (success, message) = response;
-            let prefix = if success { '=' } else { '?' };
-            let id_str = id.map(|i| i.to_string()).unwrap_or_default();
+    /// Execute one line of GTP input and write the response to `output`.
+    ///
+    /// Returns false once the engine should quit.
+    fn handle_line(&mut self, line: &str, output: &mut dyn Write) -> Result<bool> {
+        // Skip empty lines and comments
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(true);
+        }
 
-            writeln!(stdout, "{prefix}{id_str} {message}\n")?;
-            stdout.flush()?;
+        // Parse optional command ID
+        let (id, command_line) = Self::parse_id(line);
 
-            // Print turn indicator prompt to stderr
-            write!(stderr, "{} michi-rust> ", self.get_turn_indicator())?;
-            stderr.flush()?;
+        // Parse command and arguments
+        let parts: Vec<&str> = command_line.split_whitespace().collect();
+        if parts.is_empty() {
+            return Ok(true);
+        }
 
-            // Quit if requested
-            if command == "quit" {
-                break;
+        let command = parts[0].to_lowercase();
+        let args = &parts[1..];
+
+        // Execute command
+        let response = self.execute(&command, args);
+
+        // Print board after command execution (to the diagnostics, stderr in michi-c)
+        self.print_board();
+
+        // Format and send response
+        let (success, message) = response;
+        let prefix = if success { '=' } else { '?' };
+        let id_str = id.map(|i| i.to_string()).unwrap_or_default();
+
+        if success && self.analysis.is_some() {
+            // The response goes on with the reports until the next command
+            writeln!(output, "={id_str}")?;
+        } else {
+            writeln!(output, "{prefix}{id_str} {message}\n")?;
+        }
+        output.flush()?;
+
+        // Print turn indicator prompt to the diagnostics
+        let prompt = format!("{} michi-rust> ", self.get_turn_indicator());
+        self.diagnose(&prompt);
+
+        // Quit if requested
+        Ok(command != "quit")
+    }
+
+    /// Recompute the keys of every position of the game so far, after the
+    /// history was replaced or the superko rule changed.
+    fn rebuild_seen(&mut self) {
+        let superko = self.pos.rules.superko;
+        self.seen.clear();
+        for key in self
+            .history
+            .iter()
+            .chain([&self.pos])
+            .filter_map(|p| superko.key(p))
+        {
+            *self.seen.entry(key).or_default() += 1;
+        }
+    }
+
+    /// Record the move from `before` to the current position.
+    fn push_history(&mut self, before: Position) {
+        self.history.push(before);
+        if let Some(key) = self.pos.rules.superko.key(&self.pos) {
+            *self.seen.entry(key).or_default() += 1;
+        }
+    }
+
+    /// Take back the last move, returning the position before it.
+    fn pop_history(&mut self) -> Option<Position> {
+        let prev = self.history.pop()?;
+        if let Some(key) = self.pos.rules.superko.key(&self.pos)
+            && let Some(count) = self.seen.get_mut(&key)
+        {
+            *count -= 1;
+            if *count == 0 {
+                self.seen.remove(&key);
+            }
+        }
+        Some(prev)
+    }
+
+    /// Whether `pos` repeats an earlier position of the game under the
+    /// current superko rule.
+    fn repeats(&self, pos: &Position) -> bool {
+        self.pos
+            .rules
+            .superko
+            .key(pos)
+            .is_some_and(|k| self.seen.contains_key(&k))
+    }
+
+    /// Check whether playing `pt` is legal, including superko.
+    fn is_legal(&self, pt: usize) -> bool {
+        let mut next = self.pos.clone();
+        play_move(&mut next, pt).is_ok() && !self.repeats(&next)
+    }
+
+    /// Follow the move just played into the matching subtree, dropping the
+    /// rest of the tree. The tree is discarded if the move was not in it.
+    fn advance_tree(&mut self) {
+        self.tree = self.tree.take().and_then(|t| t.into_child(&self.pos));
+    }
+
+    /// Number of empty points on the board.
+    fn empty_points(&self) -> usize {
+        (BOARD_IMIN..self.pos.imax())
+            .filter(|&pt| self.pos.color[pt] == EMPTY)
+            .count()
+    }
+
+    /// Parse the arguments of `kgs-time_settings`: `none`,
+    /// `absolute <main>`, `byoyomi <main> <period> <periods>` or
+    /// `canadian <main> <period> <stones>`.
+    fn parse_kgs_time_settings(args: &[&str]) -> Option<TimeSettings> {
+        let num = |i: usize| Self::parse_seconds(args.get(i)?);
+        let count = |i: usize| args.get(i)?.parse::<u32>().ok();
+        match args.first()?.to_lowercase().as_str() {
+            "none" => Some(TimeSettings::Unlimited),
+            "absolute" => Some(TimeSettings::Absolute { main: num(1)? }),
+            "byoyomi" => Some(TimeSettings::ByoYomi {
+                main: num(1)?,
+                period: num(2)?,
+                periods: count(3)?,
+            }),
+            "canadian" => Some(TimeSettings::from_gtp(num(1)?, num(2)?, count(3)?)),
+            _ => None,
+        }
+    }
+
+    /// Parse a time in seconds, which must be finite and not negative.
+    fn parse_seconds(s: &str) -> Option<f64> {
+        s.parse::<f64>().ok().filter(|t| t.is_finite() && *t >= 0.0)
+    }
+
+    /// Restore an earlier position, keeping the komi and rules currently set.
+    fn restore(&mut self, prev: Position) {
+        let (komi, rules) = (self.pos.komi, self.pos.rules);
+        self.pos = prev;
+        self.pos.komi = komi;
+        self.pos.rules = rules;
+        self.tree = None; // Invalidate tree
+    }
+
+    /// Load the game in an SGF file, keeping the positions before move
+    /// number `until` (or the whole game). Komi and rules not given in the
+    /// file are kept from the current game.
+    fn load_sgf(&mut self, path: &str, until: Option<usize>) -> Result<()> {
+        let text = std::fs::read_to_string(path)?;
+        let root = parse_sgf(&text)?;
+        let mut positions = load_positions(&root)?;
+        for p in &mut positions {
+            if root.get("KM").is_none() {
+                p.komi = self.pos.komi;
+            }
+            if root.get("RU").is_none() {
+                p.rules = self.pos.rules;
             }
         }
 
+        let moves = until.map_or(positions.len(), |m| m.saturating_sub(1));
+        positions.truncate(moves + 1);
+        self.pos = positions.pop().expect("at least the initial position");
+        self.history = positions;
+        self.rebuild_seen();
+        self.tree = None;
+        self.owner_map.iter_mut().for_each(|x| *x = 0);
         Ok(())
     }
 
+    /// The game so far as SGF.
+    fn game_sgf(&self) -> String {
+        let mut positions = self.history.clone();
+        positions.push(self.pos.clone());
+        write_sgf(&positions)
+    }
+
+    /// Estimate the status of every group with fresh playouts from the
+    /// current position. This also refreshes the owner map shown on the board.
+    fn group_statuses(&mut self) -> Vec<(Vec<Point>, GroupStatus)> {
+        estimate_ownership(
+            &self.pos,
+            self.n_sims,
+            &self.config,
+            &mut self.owner_map,
+            &mut self.rng,
+        );
+        group_statuses(&self.pos, &self.owner_map, self.n_sims)
+    }
+
+    /// Format a score from Black's point of view as a GTP result ("B+3.5").
+    pub(crate) fn format_result(black_score: f64) -> String {
+        if black_score > 0.0 {
+            format!("B+{black_score:.1}")
+        } else if black_score < 0.0 {
+            format!("W+{:.1}", -black_score)
+        } else {
+            "0".to_string()
+        }
+    }
+
+    /// Parse a GTP color argument, returning true for Black.
+    fn parse_color(s: &str) -> Option<bool> {
+        match s.to_lowercase().as_str() {
+            "b" | "black" => Some(true),
+            "w" | "white" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Start a handicap game with Black's stones on `points` and White to
+    /// move. The board must be empty.
+    fn set_handicap(&mut self, points: &[Point]) -> (bool, String) {
+        if self
+            .pos
+            .color
+            .iter()
+            .any(|&c| c == STONE_BLACK || c == STONE_WHITE)
+        {
+            return (false, "board not empty".to_string());
+        }
+        place_handicap(&mut self.pos, points);
+        // The handicap position is where the game starts
+        self.history.clear();
+        self.rebuild_seen();
+        self.tree = Some(self.new_tree());
+        self.owner_map.iter_mut().for_each(|x| *x = 0);
+        (true, String::new())
+    }
+
+    /// The `debug` subcommands of michi-c, used by its regression suites:
+    ///
+    /// - `setpos <vertex...>` - Play the moves (or passes) in turn from the
+    ///   current position
+    /// - `fix_atari <vertex>` - `1` if the block at the vertex is in atari
+    ///   (else `0`), then the moves that capture or save it
+    /// - `match_pat <vertex>` - Ids of the large patterns matching at the vertex
+    fn debug_command(&mut self, args: &[&str]) -> (bool, String) {
+        let Some((&subcommand, args)) = args.split_first() else {
+            return (false, "missing debug subcommand".to_string());
+        };
+        match subcommand {
+            "setpos" => {
+                let mut pos = self.pos.clone();
+                let mut positions = Vec::new();
+                for vertex in args {
+                    let pt = parse_coord(vertex);
+                    if vertex.eq_ignore_ascii_case("pass") {
+                        pass_move(&mut pos);
+                    } else if !pos.contains(pt) || play_move(&mut pos, pt).is_err() {
+                        return (false, format!("illegal move {vertex}"));
+                    }
+                    positions.push(pos.clone());
+                }
+                for next in positions {
+                    let before = std::mem::replace(&mut self.pos, next);
+                    self.push_history(before);
+                }
+                self.tree = None;
+                (true, String::new())
+            }
+            "fix_atari" | "match_pat" => {
+                let Some(pt) = args.first().map(|v| parse_coord(v)) else {
+                    return (false, "missing vertex".to_string());
+                };
+                if !self.pos.contains(pt) {
+                    return (false, "invalid vertex".to_string());
+                }
+                let mut reply = Vec::new();
+                if subcommand == "fix_atari" {
+                    if self.pos.color[pt] != STONE_BLACK && self.pos.color[pt] != STONE_WHITE {
+                        return (false, "no stone at vertex".to_string());
+                    }
+                    let (_, libs) = compute_block(&self.pos, pt, 2);
+                    reply.push(u8::from(libs.len() == 1).to_string());
+                    let moves = fix_atari_ext(&self.pos, pt, false, true, false);
+                    reply.extend(moves.into_iter().map(str_coord));
+                } else {
+                    let ids = matching_pattern_ids(&self.pos, pt);
+                    reply.extend(ids.iter().map(u32::to_string));
+                }
+                (true, reply.join(" "))
+            }
+            _ => (false, format!("unknown debug subcommand: {subcommand}")),
+        }
+    }
+
     /// Parse an optional numeric command ID from the beginning of the line.
-    fn parse_id(line: &str) -> (Option<u32>, &str) {
+    pub(crate) fn parse_id(line: &str) -> (Option<u32>, &str) {
         let trimmed = line.trim();
         let mut chars = trimmed.char_indices();
 
         // Check if line starts with a digit
-        if let Some((_, c)) = chars.next() {
-            if c.is_ascii_digit() {
-                // Find end of number
-                let end = chars
-                    .find(|(_, c)| !c.is_ascii_digit())
-                    .map(|(i, _)| i)
-                    .unwrap_or(trimmed.len());
-
-                if let Ok(id) = trimmed[..end].parse::<u32>() {
-                    return (Some(id), trimmed[end..].trim());
-                }
+        if let Some((_, c)) = chars.next()
+            && c.is_ascii_digit()
+        {
+            // Find end of number
+            let end = chars
+                .find(|(_, c)| !c.is_ascii_digit())
+                .map(|(i, _)| i)
+                .unwrap_or(trimmed.len());
+
+            if let Ok(id) = trimmed[..end].parse::<u32>() {
+                return (Some(id), trimmed[end..].trim());
             }
         }
 
         (None, trimmed)
     }
 
+    /// Execute a command line without its ID, as `run` would, and return
+    /// (success, response) instead of writing them out.
+    pub fn execute_line(&mut self, line: &str) -> (bool, String) {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        match parts.split_first() {
+            Some((command, args)) => self.execute(&command.to_lowercase(), args),
+            None => (true, String::new()),
+        }
+    }
+
     /// Execute a GTP command and return (success, response).
     fn execute(&mut self, command: &str, args: &[&str]) -> (bool, String) {
         match command {
@@ -210,22 +883,71 @@ impl GtpEngine {
                     return (false, "missing argument".to_string());
                 }
                 match args[0].parse::<usize>() {
-                    Ok(size) if size == N => (true, String::new()),
-                    Ok(size) => (
-                        false,
-                        format!("unacceptable size, only {N} is supported (got {size})"),
-                    ),
+                    Ok(size) if (MIN_N..=MAX_N).contains(&size) => {
+                        let (komi, rules) = (self.pos.komi, self.pos.rules);
+                        self.pos = Position::with_size(size);
+                        self.pos.komi = komi;
+                        self.pos.rules = rules;
+                        self.history.clear();
+                        self.rebuild_seen();
+                        self.time.reset();
+                        self.tree = Some(self.new_tree());
+                        self.owner_map.iter_mut().for_each(|x| *x = 0);
+                        (true, String::new())
+                    }
+                    Ok(_) => (false, "unacceptable size".to_string()),
                     Err(_) => (false, "invalid size".to_string()),
                 }
             }
 
             "clear_board" => {
                 self.pos.clear();
-                self.tree = Some(TreeNode::new(&self.pos));
+                self.history.clear();
+                self.rebuild_seen();
+                self.time.reset();
+                self.tree = Some(self.new_tree());
                 self.owner_map.iter_mut().for_each(|x| *x = 0);
                 (true, String::new())
             }
 
+            "fixed_handicap" | "place_free_handicap" => {
+                let Some(n) = args.first().and_then(|a| a.parse::<usize>().ok()) else {
+                    return (false, "invalid number of stones".to_string());
+                };
+                let size = self.pos.size;
+                let points = if command == "fixed_handicap" {
+                    fixed_handicap_points(size, n)
+                } else {
+                    (2..size * size)
+                        .contains(&n)
+                        .then(|| free_handicap_points(&self.pos, n))
+                };
+                let Some(points) = points else {
+                    return (false, "invalid number of stones".to_string());
+                };
+                let (success, response) = self.set_handicap(&points);
+                if !success {
+                    return (success, response);
+                }
+                // These two answer with the vertices they chose
+                let vertices: Vec<String> = points.iter().map(|&pt| str_coord(pt)).collect();
+                (true, vertices.join(" "))
+            }
+
+            "set_free_handicap" => {
+                let points: Vec<Point> = args.iter().map(|v| parse_coord(v)).collect();
+                let size = self.pos.size;
+                let valid = (2..size * size).contains(&points.len())
+                    && points
+                        .iter()
+                        .enumerate()
+                        .all(|(i, &pt)| self.pos.contains(pt) && !points[..i].contains(&pt));
+                if !valid {
+                    return (false, "bad vertex list".to_string());
+                }
+                self.set_handicap(&points)
+            }
+
             "komi" => {
                 if args.is_empty() {
                     return (false, "missing argument".to_string());
@@ -233,6 +955,7 @@ impl GtpEngine {
                 match args[0].parse::<f32>() {
                     Ok(komi) => {
                         self.pos.komi = komi;
+                        self.tree = None;
                         (true, String::new())
                     }
                     Err(_) => (false, "invalid komi".to_string()),
@@ -244,29 +967,44 @@ impl GtpEngine {
                     return (false, "missing arguments".to_string());
                 }
 
-                // Parse color (ignored - we use alternating play)
-                let _color = args[0].to_lowercase();
+                let Some(black) = Self::parse_color(args[0]) else {
+                    return (false, "invalid color".to_string());
+                };
 
                 // Parse vertex
                 let vertex = args[1].to_lowercase();
                 let pt = parse_coord(&vertex);
 
                 // Handle pass
-                if vertex == "pass" || pt == PASS_MOVE {
+                if vertex == "pass" {
+                    let before = self.pos.clone();
+                    set_to_play(&mut self.pos, black);
                     pass_move(&mut self.pos);
-                    self.tree = None; // Invalidate tree
+                    self.push_history(before);
+                    self.advance_tree();
                     return (true, String::new());
                 }
 
+                // Reject vertices that are malformed or off this board
+                if !self.pos.contains(pt) {
+                    return (false, "invalid vertex".to_string());
+                }
+
                 // Check if point is empty
                 if self.pos.color[pt] != b'.' {
                     return (false, "illegal move".to_string());
                 }
 
                 // Try to play the move
-                match play_move(&mut self.pos, pt) {
+                let before = self.pos.clone();
+                match play_move_for(&mut self.pos, pt, black) {
+                    Ok(()) if self.repeats(&self.pos) => {
+                        self.pos = before;
+                        (false, MoveError::Superko.to_string())
+                    }
                     Ok(()) => {
-                        self.tree = None; // Invalidate tree
+                        self.push_history(before);
+                        self.advance_tree();
                         (true, String::new())
                     }
                     Err(e) => (false, e.to_string()),
@@ -277,46 +1015,322 @@ impl GtpEngine {
                 if args.is_empty() {
                     return (false, "missing argument".to_string());
                 }
-
-                // If opponent passed and we're past the opening, pass too
-                if self.pos.last == PASS_MOVE && self.pos.n > 2 {
+                let Some(black) = Self::parse_color(args[0]) else {
+                    return (false, "invalid color".to_string());
+                };
+                let before = self.pos.clone();
+                let start = Instant::now();
+
+                // If opponent passed and we're past the opening, pass too.
+                // An implicit pass (generating out of turn) does not count.
+                if set_to_play(&mut self.pos, black) {
+                    self.tree = None;
+                } else if self.pos.last == PASS_MOVE && self.pos.n > 2 {
                     pass_move(&mut self.pos);
+                    self.push_history(before);
+                    self.advance_tree();
+                    self.time.record(black, start.elapsed().as_secs_f64());
                     return (true, "pass".to_string());
                 }
 
-                // Create fresh tree for search with display
-                let mut tree = TreeNode::new(&self.pos);
-                // Clear owner map before search
-                self.owner_map.iter_mut().for_each(|x| *x = 0);
-                let pt = tree_search_with_display(&mut tree, self.n_sims, &mut self.owner_map);
+                // Continue from the tree of the previous moves if it is at
+                // this position, dropping moves that would repeat an earlier one
+                let mut tree = match self.tree.take() {
+                    Some(tree) if tree.is_at(&self.pos) => tree,
+                    _ => self.new_tree(),
+                };
+                self.expand_root(&mut tree);
+                // An interrupt sent before this search does not stop it
+                self.cancel.reset();
+                // Under time controls the clock, not the simulation count,
+                // limits the search
+                let deadline = self
+                    .time
+                    .budget(black, self.empty_points())
+                    .map(|b| b.deadline(start));
+                let sims = if deadline.is_some() {
+                    u32::MAX as usize
+                } else {
+                    self.n_sims
+                };
+                let mut log;
+                let observer: &mut dyn SearchObserver = match self.diagnostics.as_mut() {
+                    Some(out) => {
+                        log = LogObserver::new(&self.config, out);
+                        &mut log
+                    }
+                    None => &mut NullObserver,
+                };
+                let result = tree_search_parallel(
+                    &mut tree,
+                    sims,
+                    self.threads,
+                    &self.config,
+                    &mut self.owner_map,
+                    &SearchLimits {
+                        deadline,
+                        cancel: Some(self.cancel.clone()),
+                    },
+                    observer,
+                );
+                self.time.record(black, start.elapsed().as_secs_f64());
 
                 // Check for resignation
-                let winrate = tree
-                    .children
-                    .iter()
-                    .max_by_key(|c| c.v)
-                    .map(|c| c.winrate())
-                    .unwrap_or(0.0);
-
-                if winrate < RESIGN_THRES && pt != PASS_MOVE {
+                let pt = result.best_move;
+                if result.winrate < self.config.resign_thres && pt != PASS_MOVE {
+                    // Resigning leaves the board as it was
+                    self.pos = before;
+                    self.tree = Some(tree);
                     return (true, "resign".to_string());
                 }
 
                 // Play the move
-                if pt == PASS_MOVE || pt == RESIGN_MOVE {
+                let reply = if pt == PASS_MOVE || pt == RESIGN_MOVE {
                     pass_move(&mut self.pos);
-                    (true, "pass".to_string())
+                    "pass".to_string()
                 } else {
                     play_move(&mut self.pos, pt).unwrap();
-                    (true, str_coord(pt))
+                    str_coord(pt)
+                };
+                self.push_history(before);
+                self.tree = tree.into_child(&self.pos);
+                (true, reply)
+            }
+
+            "undo" => match self.pop_history() {
+                Some(prev) => {
+                    self.restore(prev);
+                    (true, String::new())
+                }
+                None => (false, "cannot undo".to_string()),
+            },
+
+            "time_settings" => {
+                if args.len() < 3 {
+                    return (false, "missing arguments".to_string());
+                }
+                match (
+                    Self::parse_seconds(args[0]),
+                    Self::parse_seconds(args[1]),
+                    args[2].parse::<u32>(),
+                ) {
+                    (Some(main), Some(period), Ok(stones)) => {
+                        self.time.set(TimeSettings::from_gtp(main, period, stones));
+                        (true, String::new())
+                    }
+                    _ => (false, "invalid time settings".to_string()),
+                }
+            }
+
+            "kgs-time_settings" => match Self::parse_kgs_time_settings(args) {
+                Some(settings) => {
+                    self.time.set(settings);
+                    (true, String::new())
+                }
+                None => (false, "invalid time settings".to_string()),
+            },
+
+            "time_left" => {
+                if args.len() < 3 {
+                    return (false, "missing arguments".to_string());
+                }
+                let Some(black) = Self::parse_color(args[0]) else {
+                    return (false, "invalid color".to_string());
+                };
+                match (Self::parse_seconds(args[1]), args[2].parse::<u32>()) {
+                    (Some(time), Ok(stones)) => {
+                        self.time.time_left(black, time, stones);
+                        (true, String::new())
+                    }
+                    _ => (false, "invalid time".to_string()),
                 }
             }
 
+            "michi-superko" => {
+                if args.is_empty() {
+                    return (true, self.pos.rules.superko.name().to_string());
+                }
+                match Superko::from_name(args[0]) {
+                    Some(rule) => {
+                        self.pos.rules.superko = rule;
+                        self.rebuild_seen();
+                        (true, String::new())
+                    }
+                    None => (false, "unknown superko rule".to_string()),
+                }
+            }
+
+            "lz-analyze" | "kata-analyze" => {
+                let Some((color, interval, ownership)) = Self::parse_analyze_args(args) else {
+                    return (false, "invalid arguments".to_string());
+                };
+                // Analyzing for the side not to move assumes a pass first,
+                // without playing it
+                let mut pos = self.pos.clone();
+                if let Some(black) = color {
+                    set_to_play(&mut pos, black);
+                }
+                let mut tree = match self.tree.take() {
+                    Some(tree) if tree.is_at(&pos) => tree,
+                    _ => self.new_tree_at(&pos),
+                };
+                self.expand_root(&mut tree);
+                self.tree = Some(tree);
+                self.owner_map.iter_mut().for_each(|x| *x = 0);
+                let kata = command == "kata-analyze";
+                self.analysis = Some(Analysis {
+                    kata,
+                    ownership: kata && ownership,
+                    interval: Duration::from_millis(interval.saturating_mul(10)),
+                    last_report: Instant::now(),
+                    playouts: 0,
+                });
+                (true, String::new())
+            }
+
+            "michi-param" => match args {
+                [] => {
+                    let lines: Vec<String> = SearchConfig::NAMES
+                        .iter()
+                        .map(|name| format!("{name} {}", self.config.get(name).unwrap()))
+                        .collect();
+                    (true, lines.join("\n"))
+                }
+                [name] => match self.config.get(name) {
+                    Some(value) => (true, value),
+                    None => (false, format!("unknown parameter {name}")),
+                },
+                [name, value, ..] => match self.config.set(name, value) {
+                    Ok(()) => (true, String::new()),
+                    Err(e) => (false, e.to_string()),
+                },
+            },
+
+            "michi-seed" => match args.first() {
+                None => (true, self.seed.to_string()),
+                Some(seed) => match seed.parse() {
+                    Ok(seed) => {
+                        self.reseed(seed);
+                        (true, String::new())
+                    }
+                    Err(_) => (false, "invalid seed".to_string()),
+                },
+            },
+
+            "kgs-rules" => {
+                if args.is_empty() {
+                    return (false, "missing argument".to_string());
+                }
+                match Rules::from_name(args[0]) {
+                    Some(rules) => {
+                        self.pos.rules = rules;
+                        self.rebuild_seen();
+                        self.tree = None;
+                        (true, String::new())
+                    }
+                    None => (false, "unknown rules".to_string()),
+                }
+            }
+
+            // The interrupt itself is handled by the reader thread in `run`
+            "gogui-interrupt" => (true, String::new()),
+            "gogui-rules_game_id" => (true, "Go".to_string()),
+
+            "gogui-rules_board_size" => (true, self.pos.size.to_string()),
+
+            "gogui-rules_side_to_move" => {
+                let side = if self.pos.is_black_to_play() {
+                    "black"
+                } else {
+                    "white"
+                };
+                (true, side.to_string())
+            }
+
+            "gogui-rules_legal_moves" => {
+                let mut moves: Vec<String> = (BOARD_IMIN..self.pos.imax())
+                    .filter(|&pt| self.pos.color[pt] == EMPTY && self.is_legal(pt))
+                    .map(str_coord)
+                    .collect();
+                moves.push("pass".to_string());
+                (true, moves.join(" "))
+            }
+
+            "gogui-rules_final_result" => {
+                // Counts the board as it stands, without removing dead stones
+                let s = score(&self.pos);
+                let black_score = if self.pos.is_black_to_play() { s } else { -s };
+                (true, Self::format_result(black_score))
+            }
+
+            "loadsgf" => {
+                if args.is_empty() {
+                    return (false, "missing argument".to_string());
+                }
+                let until = match args.get(1).map(|m| m.parse::<usize>()) {
+                    None => None,
+                    Some(Ok(m)) => Some(m),
+                    Some(Err(_)) => return (false, "invalid move number".to_string()),
+                };
+                match self.load_sgf(args[0], until) {
+                    Ok(()) => (true, String::new()),
+                    Err(e) => (false, format!("cannot load file: {e}")),
+                }
+            }
+
+            "printsgf" => {
+                let sgf = self.game_sgf();
+                match args.first() {
+                    Some(path) => match std::fs::write(path, sgf) {
+                        Ok(()) => (true, String::new()),
+                        Err(e) => (false, format!("cannot write file: {e}")),
+                    },
+                    None => (true, sgf.trim_end().to_string()),
+                }
+            }
+
+            "final_score" => {
+                let dead: Vec<Point> = self
+                    .group_statuses()
+                    .into_iter()
+                    .filter(|(_, status)| *status == GroupStatus::Dead)
+                    .flat_map(|(stones, _)| stones)
+                    .collect();
+                let s = final_score(&self.pos, &dead);
+                let black_score = if self.pos.is_black_to_play() { s } else { -s };
+                (true, Self::format_result(black_score))
+            }
+
+            "final_status_list" => {
+                if args.is_empty() {
+                    return (false, "missing argument".to_string());
+                }
+                let wanted = match args[0].to_lowercase().as_str() {
+                    "alive" => GroupStatus::Alive,
+                    "dead" => GroupStatus::Dead,
+                    "seki" => GroupStatus::Seki,
+                    _ => return (false, "invalid status".to_string()),
+                };
+                let lines: Vec<String> = self
+                    .group_statuses()
+                    .into_iter()
+                    .filter(|(_, status)| *status == wanted)
+                    .map(|(mut stones, _)| {
+                        stones.sort_unstable();
+                        let vertices: Vec<String> = stones.into_iter().map(str_coord).collect();
+                        vertices.join(" ")
+                    })
+                    .collect();
+                (true, lines.join("\n"))
+            }
+
+            "debug" => self.debug_command(args),
+
             "showboard" => {
-                // Output the board to stderr (GTP debug output) and return empty success
+                // Output the board to the diagnostics and return it as the response too
                 let board_str =
                     format_position_with_owner(&self.pos, Some(&self.owner_map), self.n_sims);
-                eprint!("{}", board_str);
+                self.diagnose(&board_str);
                 (true, format!("\n{}", board_str.trim_end()))
             }
 
@@ -338,6 +1352,15 @@ impl GtpEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::constants::MAX_MOVE_TIME;
+
+    /// Play `moves` in order, asserting that each one is accepted.
+    fn play_all(engine: &mut GtpEngine, moves: &[(&str, &str)]) {
+        for &(color, vertex) in moves {
+            let (success, response) = engine.execute("play", &[color, vertex]);
+            assert!(success, "play {color} {vertex}: {response}");
+        }
+    }
 
     #[test]
     fn test_parse_id_with_id() {
@@ -386,13 +1409,746 @@ mod tests {
     fn test_boardsize() {
         let mut engine = GtpEngine::new();
 
-        // Correct size
-        let (success, _) = engine.execute("boardsize", &[&N.to_string()]);
+        // Every supported size is accepted and resizes the board
+        for size in ["9", "13", "19"] {
+            let (success, _) = engine.execute("boardsize", &[size]);
+            assert!(success);
+            assert_eq!(engine.pos.size.to_string(), size);
+        }
+
+        // Unsupported size
+        let (success, _) = engine.execute("boardsize", &["25"]);
+        assert!(!success);
+        assert_eq!(engine.pos.size, 19);
+    }
+
+    #[test]
+    fn test_boardsize_keeps_komi() {
+        let mut engine = GtpEngine::new();
+        engine.execute("komi", &["6.5"]);
+        engine.execute("play", &["black", "D4"]);
+        let (success, _) = engine.execute("boardsize", &["13"]);
         assert!(success);
+        assert_eq!(engine.pos.n, 0);
+        assert_eq!(engine.pos.komi, 6.5);
+    }
+
+    #[test]
+    fn test_play_19x19_vertices() {
+        let mut engine = GtpEngine::new();
+
+        // T19 is off the 9x9 board
+        let (success, response) = engine.execute("play", &["black", "T19"]);
+        assert!(!success);
+        assert_eq!(response, "invalid vertex");
+        assert_eq!(engine.pos.n, 0);
+
+        engine.execute("boardsize", &["19"]);
+        play_all(
+            &mut engine,
+            &[("black", "T19"), ("white", "A1"), ("black", "K10")],
+        );
+        assert_eq!(engine.pos.n, 3);
 
-        // Wrong size
-        let (success, _) = engine.execute("boardsize", &["19"]);
+        // Garbage is not silently treated as a pass
+        let (success, _) = engine.execute("play", &["white", "U1"]);
         assert!(!success);
+        assert_eq!(engine.pos.n, 3);
+    }
+
+    #[test]
+    fn test_genmove_19x19() {
+        let mut engine = GtpEngine::with_simulations(20);
+        engine.execute("boardsize", &["19"]);
+        engine.execute("play", &["black", "D4"]);
+        let (success, response) = engine.execute("genmove", &["white"]);
+        assert!(success);
+        let pt = parse_coord(&response);
+        assert!(response == "resign" || response == "pass" || engine.pos.contains(pt));
+    }
+
+    #[test]
+    fn test_debug_commands() {
+        let mut engine = GtpEngine::new();
+        let mut debug = |args: &[&str]| engine.execute("debug", args);
+
+        // fix_atari.tst tests 20 and 30
+        assert!(debug(&["setpos", "C1", "G7", "B2", "B1"]).0);
+        assert_eq!(debug(&["fix_atari", "B1"]), (true, "1 A1".to_string()));
+        assert!(debug(&["setpos", "E5"]).0);
+        assert_eq!(debug(&["fix_atari", "B1"]), (true, "1".to_string()));
+        assert_eq!(debug(&["fix_atari", "G7"]), (true, "0".to_string()));
+
+        assert!(!debug(&["fix_atari", "D4"]).0, "no stone");
+        assert!(!debug(&["setpos", "E5"]).0, "occupied");
+        assert!(!debug(&["setpos", "Z3"]).0);
+        assert!(!debug(&["bogus"]).0);
+        assert!(!debug(&[]).0);
+
+        // setpos plays moves in turn, so they can be undone
+        assert!(debug(&["setpos", "pass", "D4"]).0);
+        assert!(!engine.pos.is_black_to_play());
+        assert!(engine.execute("undo", &[]).0);
+        assert_eq!(engine.pos.color[parse_coord("D4")], EMPTY);
+
+        assert!(engine.execute("debug", &["match_pat", "E4"]).0);
+        assert!(!engine.execute("debug", &["match_pat", "pass"]).0);
+    }
+
+    #[test]
+    fn test_handicap_commands() {
+        let mut engine = GtpEngine::with_simulations(10);
+        assert_eq!(
+            engine.execute("fixed_handicap", &["2"]),
+            (true, "C3 G7".to_string())
+        );
+        assert_eq!(engine.pos.handicap, 2);
+        assert_eq!(engine.execute("gogui-rules_side_to_move", &[]).1, "white");
+        assert_eq!(
+            engine.execute("fixed_handicap", &["2"]),
+            (false, "board not empty".to_string())
+        );
+
+        // The game record starts from the handicap position
+        assert!(engine.execute("genmove", &["white"]).0);
+        let sgf = engine.execute("printsgf", &[]).1;
+        assert!(sgf.contains("HA[2]") && sgf.contains("AB[cg][gc]") && sgf.contains("PL[W]"));
+
+        engine.execute("clear_board", &[]);
+        assert_eq!(engine.pos.handicap, 0);
+        assert!(!engine.execute("fixed_handicap", &["10"]).0);
+        assert!(!engine.execute("place_free_handicap", &["81"]).0);
+        let (success, vertices) = engine.execute("place_free_handicap", &["12"]);
+        assert!(success);
+        assert_eq!(vertices.split(' ').count(), 12);
+
+        engine.execute("clear_board", &[]);
+        for bad in [&["A1"][..], &["A1", "pass"], &["A1", "A1"], &["A1", "Z9"]] {
+            assert!(!engine.execute("set_free_handicap", bad).0);
+        }
+        assert_eq!(
+            engine.execute("set_free_handicap", &["A1", "J9", "E5"]),
+            (true, String::new())
+        );
+        assert_eq!(engine.pos.handicap, 3);
+        assert!(!engine.pos.is_black_to_play());
+        assert!(!engine.execute("play", &["white", "E5"]).0);
+
+        // A handicap may not fill the whole board
+        engine.execute("clear_board", &[]);
+        let full: Vec<String> = (1..=9)
+            .flat_map(|row| "ABCDEFGHJ".chars().map(move |col| format!("{col}{row}")))
+            .collect();
+        let full: Vec<&str> = full.iter().map(String::as_str).collect();
+        assert_eq!(
+            engine.execute("set_free_handicap", &full),
+            (false, "bad vertex list".to_string())
+        );
+        assert!(engine.execute("set_free_handicap", &full[..80]).0);
+    }
+
+    #[test]
+    fn test_play_out_of_turn() {
+        let mut engine = GtpEngine::new();
+
+        // Two black moves in a row (e.g. handicap setup)
+        assert!(engine.execute("play", &["black", "C3"]).0);
+        assert!(engine.execute("play", &["B", "G7"]).0);
+        assert_eq!(engine.pos.n, 3);
+        assert!(!engine.pos.is_black_to_play());
+
+        // White pass out of turn, then Black is to move
+        assert!(engine.execute("play", &["white", "pass"]).0);
+        assert!(engine.pos.is_black_to_play());
+
+        let (success, response) = engine.execute("play", &["red", "D4"]);
+        assert!(!success);
+        assert_eq!(response, "invalid color");
+    }
+
+    #[test]
+    fn test_genmove_out_of_turn() {
+        let mut engine = GtpEngine::with_simulations(10);
+        play_all(
+            &mut engine,
+            &[("black", "E5"), ("white", "C3"), ("black", "G7")],
+        );
+
+        // White to move; the implicit White pass must not be answered by a pass
+        let (success, response) = engine.execute("genmove", &["black"]);
+        assert!(success);
+        assert_ne!(response, "pass");
+        if response != "resign" {
+            assert_eq!(engine.pos.n, 5);
+            assert!(!engine.pos.is_black_to_play());
+        }
+    }
+
+    #[test]
+    fn test_undo_restores_position() {
+        let mut engine = GtpEngine::new();
+        play_all(
+            &mut engine,
+            &[
+                ("black", "C5"),
+                ("white", "E6"),
+                ("black", "D6"),
+                ("white", "F5"),
+                ("black", "D4"),
+                ("white", "E4"),
+                ("black", "A1"),
+                ("white", "D5"),
+            ],
+        );
+        let before = engine.pos.clone();
+
+        // Black captures D5, White may not retake the ko immediately
+        assert!(engine.execute("play", &["black", "E5"]).0);
+        let after_capture = engine.pos.clone();
+        assert_eq!(after_capture.ko, parse_coord("D5"));
+        assert!(!engine.execute("play", &["white", "D5"]).0);
+        assert!(engine.execute("play", &["white", "pass"]).0);
+        assert_eq!(engine.pos.ko, 0);
+
+        // Undo the pass: ko and captures are back
+        assert!(engine.execute("undo", &[]).0);
+        assert_eq!(engine.pos.color, after_capture.color);
+        assert_eq!(engine.pos.ko, after_capture.ko);
+        assert_eq!(engine.pos.cap, after_capture.cap);
+        assert_eq!(engine.pos.n, after_capture.n);
+
+        // Undo the capture: D5 is back on the board
+        assert!(engine.execute("undo", &[]).0);
+        assert_eq!(engine.pos.color, before.color);
+        assert_eq!(engine.pos.env4, before.env4);
+        assert_eq!(engine.pos.ko, before.ko);
+        assert_eq!(
+            (engine.pos.cap, engine.pos.cap_x),
+            (before.cap, before.cap_x)
+        );
+        assert_eq!(engine.pos.n, before.n);
+        assert_eq!(engine.pos.last, before.last);
+    }
+
+    #[test]
+    fn test_undo_capture() {
+        let mut engine = GtpEngine::new();
+        play_all(
+            &mut engine,
+            &[("black", "A2"), ("white", "A1"), ("black", "B1")],
+        );
+        // A1 was captured
+        assert_eq!(engine.pos.color[parse_coord("A1")], b'.');
+
+        assert!(engine.execute("undo", &[]).0);
+        assert_eq!(engine.pos.n, 2);
+        assert!(engine.pos.is_black_to_play());
+        assert_eq!(engine.pos.color[parse_coord("A1")], b'x');
+        assert_eq!(engine.pos.color[parse_coord("B1")], b'.');
+        assert_eq!(engine.pos.cap + engine.pos.cap_x, 0);
+    }
+
+    #[test]
+    fn test_undo_out_of_turn_and_genmove() {
+        let mut engine = GtpEngine::with_simulations(10);
+        engine.execute("play", &["black", "C3"]);
+        engine.execute("play", &["black", "G7"]);
+        // One undo takes back the stone and its implicit pass
+        assert!(engine.execute("undo", &[]).0);
+        assert_eq!(engine.pos.n, 1);
+        assert_eq!(engine.pos.last, parse_coord("C3"));
+
+        let (_, response) = engine.execute("genmove", &["white"]);
+        if response != "resign" {
+            assert!(engine.execute("undo", &[]).0);
+        }
+        assert_eq!(engine.pos.n, 1);
+
+        assert!(engine.execute("undo", &[]).0);
+        assert_eq!(engine.pos.n, 0);
+        let (success, response) = engine.execute("undo", &[]);
+        assert!(!success);
+        assert_eq!(response, "cannot undo");
+    }
+
+    #[test]
+    fn test_tree_reused_between_moves() {
+        fn to_play(pos: &Position) -> &'static str {
+            if pos.is_black_to_play() {
+                "black"
+            } else {
+                "white"
+            }
+        }
+
+        let mut engine = GtpEngine::with_simulations(50);
+        engine.execute("genmove", &["black"]);
+        // The tree follows our move (or stays put if we resigned)
+        assert!(engine.tree.as_ref().unwrap().is_at(&engine.pos));
+
+        // Expand two moves deep, then follow them down the tree
+        let tree = engine.tree.as_mut().unwrap();
+        let mut pos = tree.pos().clone();
+        crate::mcts::expand(tree, Tree::ROOT, &pos, &engine.config);
+        let child = tree.child_ids(Tree::ROOT).start;
+        let reply = tree.node(child).mv();
+        Tree::replay(&mut pos, reply);
+        crate::mcts::expand(tree, child, &pos, &engine.config);
+        tree.node_mut(child).v = 7;
+        let answer = tree.children(child)[0].mv();
+
+        engine.execute("play", &[to_play(&engine.pos), &str_coord(reply)]);
+        let tree = engine.tree.as_ref().expect("subtree of the reply");
+        assert!(tree.is_at(&engine.pos));
+        assert_eq!(tree.root().v, 7, "statistics are kept");
+        assert!(tree.root().is_expanded());
+
+        engine.execute("play", &[to_play(&engine.pos), &str_coord(answer)]);
+        assert!(engine.tree.as_ref().unwrap().is_at(&engine.pos));
+
+        // Undo and komi changes start afresh
+        engine.execute("undo", &[]);
+        assert!(engine.tree.is_none());
+        engine.execute("genmove", &["black"]);
+        engine.execute("komi", &["6.5"]);
+        assert!(engine.tree.is_none());
+    }
+
+    #[test]
+    fn test_ponder_extends_tree_for_next_move() {
+        let mut engine = GtpEngine::with_simulations(100).with_ponder(true);
+        engine.execute("play", &["black", "E5"]);
+        assert!(engine.tree.is_none(), "E5 was not searched");
+        assert!(!engine.ponder_step(), "no tree to ponder");
+
+        engine.execute("genmove", &["white"]);
+        let before = engine.tree.as_ref().map_or(0, |t| t.root().v);
+        let mut steps = 0;
+        while engine.ponder_step() {
+            steps += 1;
+        }
+        let tree = engine.tree.as_ref().unwrap();
+        assert!(steps > 0);
+        assert!(tree.root().v > before);
+        assert!(tree.root().v as usize >= 100 * PONDER_SIMS_FACTOR);
+
+        // The opponent's reply keeps what pondering found below it
+        let reply = tree
+            .children(Tree::ROOT)
+            .iter()
+            .max_by_key(|c| c.v)
+            .unwrap();
+        let (pt, visits) = (reply.mv(), reply.v);
+        let color = if engine.pos.is_black_to_play() {
+            "black"
+        } else {
+            "white"
+        };
+        engine.execute("play", &[color, &str_coord(pt)]);
+        assert_eq!(engine.tree.as_ref().unwrap().root().v, visits);
+    }
+
+    #[test]
+    fn test_time_settings() {
+        let mut engine = GtpEngine::new();
+        assert!(engine.execute("time_settings", &["300", "30", "5"]).0);
+        assert_eq!(
+            engine.time.settings(),
+            TimeSettings::Canadian {
+                main: 300.0,
+                period: 30.0,
+                stones: 5
+            }
+        );
+        assert!(!engine.execute("time_settings", &["300", "x", "5"]).0);
+
+        assert!(
+            engine
+                .execute("kgs-time_settings", &["byoyomi", "0", "10", "3"])
+                .0
+        );
+        assert_eq!(
+            engine.time.settings(),
+            TimeSettings::ByoYomi {
+                main: 0.0,
+                period: 10.0,
+                periods: 3
+            }
+        );
+        assert!(engine.execute("kgs-time_settings", &["absolute", "60"]).0);
+        assert!(engine.execute("kgs-time_settings", &["none"]).0);
+        assert_eq!(engine.time.settings(), TimeSettings::Unlimited);
+        assert!(!engine.execute("kgs-time_settings", &["canadian", "60"]).0);
+        assert!(!engine.execute("kgs-time_settings", &["hourglass", "60"]).0);
+
+        assert!(engine.execute("time_left", &["white", "42", "0"]).0);
+        assert_eq!(engine.time.clock(false).time, 42.0);
+        assert!(!engine.execute("time_left", &["red", "42", "0"]).0);
+
+        // Times must be finite and not negative
+        for bad in [["-5", "0", "0"], ["300", "inf", "1"], ["NaN", "0", "0"]] {
+            assert!(!engine.execute("time_settings", &bad).0, "{bad:?}");
+        }
+        // A huge but finite clock gives a capped budget rather than a panic
+        assert!(engine.execute("time_settings", &["1e30", "0", "0"]).0);
+        let budget = engine.time.budget(true, 81).unwrap();
+        assert_eq!(budget.hard, Duration::from_secs_f64(MAX_MOVE_TIME));
+        for bad in [
+            &["absolute", "inf"][..],
+            &["byoyomi", "-1", "10", "3"],
+            &["canadian", "0", "NaN", "5"],
+        ] {
+            assert!(!engine.execute("kgs-time_settings", bad).0, "{bad:?}");
+        }
+        for bad in ["inf", "-1", "nan"] {
+            assert!(
+                !engine.execute("time_left", &["black", bad, "0"]).0,
+                "{bad}"
+            );
+        }
+
+        // Passing back without a search still counts as a move
+        assert!(engine.execute("time_settings", &["0", "30", "5"]).0);
+        assert!(engine.execute("time_left", &["white", "30", "5"]).0);
+        play_all(
+            &mut engine,
+            &[("black", "D4"), ("white", "E5"), ("black", "pass")],
+        );
+        assert_eq!(engine.execute("genmove", &["white"]).1, "pass");
+        assert_eq!(engine.time.clock(false).stones, 4);
+    }
+
+    #[test]
+    fn test_genmove_respects_time_budget() {
+        // One second per move: the budget is what is left after the margin,
+        // where a fixed simulation count would take far longer
+        let mut engine = GtpEngine::with_simulations(10_000_000);
+        engine.execute("time_settings", &["0", "1", "1"]);
+        let start = Instant::now();
+        let (success, _) = engine.execute("genmove", &["black"]);
+        assert!(success);
+        assert!(start.elapsed().as_secs_f64() < 2.0);
+    }
+
+    #[test]
+    fn test_genmove_cancelled() {
+        let mut engine = GtpEngine::with_simulations(10_000_000);
+        engine.execute("michi-param", &["fastplay5_thres", "1"]);
+        engine.execute("michi-param", &["fastplay20_thres", "1"]);
+        assert!(engine.execute("known_command", &["gogui-interrupt"]).0);
+
+        // A stale interrupt does not stop the next search early...
+        let cancel = engine.cancel_handle();
+        cancel.cancel();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            cancel.cancel();
+        });
+        // ...but one sent during it does, and a move is still played
+        let start = Instant::now();
+        let (success, response) = engine.execute("genmove", &["black"]);
+        canceller.join().unwrap();
+        assert!(success);
+        assert!(engine.pos.contains(parse_coord(&response)));
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(100) && elapsed.as_secs_f64() < 2.0);
+    }
+
+    #[test]
+    fn test_superko() {
+        let mut engine = GtpEngine::new();
+        let (_, rule) = engine.execute("michi-superko", &[]);
+        assert_eq!(rule, "positional");
+
+        // Black takes the ko at D5, both pass, then White retakes: this
+        // recreates the position before Black's capture
+        play_all(
+            &mut engine,
+            &[
+                ("black", "C5"),
+                ("white", "E6"),
+                ("black", "D6"),
+                ("white", "F5"),
+                ("black", "D4"),
+                ("white", "E4"),
+                ("black", "A1"),
+                ("white", "D5"),
+                ("black", "E5"),
+                ("white", "pass"),
+                ("black", "pass"),
+            ],
+        );
+        let n = engine.pos.n;
+        let (success, response) = engine.execute("play", &["white", "D5"]);
+        assert!(!success);
+        assert!(response.contains("superko"), "got: {response}");
+        assert_eq!(engine.pos.n, n);
+
+        // Only simple ko without superko
+        assert!(engine.execute("michi-superko", &["off"]).0);
+        assert!(engine.execute("play", &["white", "D5"]).0);
+
+        // Turning superko back on covers the moves played without it, and
+        // taking back one of two repeated positions keeps the other
+        assert!(engine.execute("michi-superko", &["positional"]).0);
+        assert!(engine.execute("undo", &[]).0);
+        assert!(!engine.execute("play", &["white", "D5"]).0);
+
+        // Undone moves no longer count
+        for _ in 0..3 {
+            assert!(engine.execute("undo", &[]).0);
+        }
+        assert!(engine.execute("play", &["black", "E5"]).0);
+
+        assert!(!engine.execute("michi-superko", &["chinese"]).0);
+    }
+
+    #[test]
+    fn test_michi_param() {
+        let mut engine = GtpEngine::with_simulations(10);
+        let (success, listing) = engine.execute("michi-param", &[]);
+        assert!(success);
+        assert_eq!(listing.lines().count(), SearchConfig::NAMES.len());
+        assert!(listing.contains("rave_equiv 3500"));
+
+        assert!(engine.execute("michi-param", &["expand_visits", "4"]).0);
+        assert_eq!(engine.execute("michi-param", &["expand_visits"]).1, "4");
+        assert_eq!(engine.config.expand_visits, 4);
+
+        assert!(!engine.execute("michi-param", &["expand_visits", "x"]).0);
+        assert!(!engine.execute("michi-param", &["no_such_param"]).0);
+
+        // Never resign, whatever the search finds
+        engine.execute("michi-param", &["resign_thres", "0"]);
+        assert_ne!(engine.execute("genmove", &["black"]).1, "resign");
+    }
+
+    #[test]
+    fn test_michi_seed() {
+        let game = |engine: &mut GtpEngine| {
+            engine.execute("clear_board", &[]);
+            let moves: Vec<String> = ["black", "white", "black"]
+                .iter()
+                .map(|color| engine.execute("genmove", &[color]).1)
+                .collect();
+            let score = engine.execute("final_score", &[]).1;
+            (moves, score)
+        };
+
+        let mut engine = GtpEngine::with_simulations(30).with_seed(5);
+        assert_eq!(engine.execute("michi-seed", &[]).1, "5");
+        let first = game(&mut engine);
+
+        // Reseeding replays the session exactly
+        assert!(engine.execute("michi-seed", &["5"]).0);
+        assert_eq!(game(&mut engine), first);
+        assert_eq!(
+            game(&mut GtpEngine::with_simulations(30).with_seed(5)),
+            first
+        );
+
+        assert!(!engine.execute("michi-seed", &["x"]).0);
+    }
+
+    /// Run analysis batches until a report comes out.
+    fn next_report(engine: &mut GtpEngine) -> String {
+        (0..20)
+            .find_map(|_| engine.analyze_step())
+            .expect("analysis report")
+    }
+
+    #[test]
+    fn test_lz_analyze() {
+        let mut engine = GtpEngine::with_simulations(10);
+        engine.execute("play", &["black", "E5"]);
+        assert!(engine.execute("lz-analyze", &["0"]).0);
+
+        let info = next_report(&mut engine);
+        let first: Vec<&str> = info.split(" info ").next().unwrap().split(' ').collect();
+        assert_eq!(first[..2], ["info", "move"]);
+        assert_eq!(first[3], "visits");
+        assert_eq!(first[5], "winrate");
+        assert!(first[6].parse::<u32>().unwrap() <= 10000);
+        assert_eq!(first[7], "prior");
+        assert!(first[8].parse::<u32>().unwrap() <= 10000);
+        assert_eq!(first[9..11], ["order", "0"]);
+        assert_eq!(first[11], "pv");
+        assert_eq!(first[12], first[2], "the pv starts with the move");
+        assert!(!info.contains("ownership"));
+
+        // The analysis searched the current position, for the next genmove
+        engine.analysis = None;
+        let visits = engine.tree.as_ref().unwrap().root().v;
+        assert!(visits > 0);
+        assert!(engine.tree.as_ref().unwrap().is_at(&engine.pos));
+
+        assert!(!engine.execute("lz-analyze", &["often"]).0);
+        assert!(engine.analysis.is_none());
+
+        // An interval too long for a Duration in milliseconds is capped
+        assert!(engine.execute("lz-analyze", &["2000000000000000000"]).0);
+        assert_eq!(
+            engine.analysis.as_ref().unwrap().interval,
+            Duration::from_millis(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_kata_analyze_ownership() {
+        let mut engine = GtpEngine::with_simulations(10);
+        let args = ["b", "interval", "0", "ownership", "true"];
+        assert!(engine.execute("kata-analyze", &args).0);
+
+        let info = next_report(&mut engine);
+        let (moves, ownership) = info.split_once(" ownership ").unwrap();
+        let winrate: f64 = moves.split(' ').nth(6).unwrap().parse().unwrap();
+        assert!((0.0..=1.0).contains(&winrate));
+        let values: Vec<f64> = ownership.split(' ').map(|v| v.parse().unwrap()).collect();
+        assert_eq!(values.len(), 81);
+        assert!(values.iter().all(|v| (-1.0..=1.0).contains(v)));
+
+        // Analyzing for White on Black's turn assumes a pass
+        engine.analysis = None;
+        assert!(engine.execute("kata-analyze", &["white", "0"]).0);
+        next_report(&mut engine);
+        assert!(!engine.tree.as_ref().unwrap().pos().is_black_to_play());
+        assert!(engine.pos.is_black_to_play());
+    }
+
+    #[test]
+    fn test_kgs_rules() {
+        let mut engine = GtpEngine::new();
+        assert_eq!(engine.pos.rules, Rules::chinese());
+
+        assert!(engine.execute("kgs-rules", &["japanese"]).0);
+        assert_eq!(engine.pos.rules, Rules::japanese());
+        assert_eq!(engine.execute("michi-superko", &[]).1, "off");
+        assert!(!engine.execute("kgs-rules", &["ing"]).0);
+
+        // Rules and komi belong to the game, not to a board snapshot
+        engine.execute("komi", &["6.5"]);
+        engine.execute("play", &["black", "E5"]);
+        assert!(engine.execute("kgs-rules", &["aga"]).0);
+        engine.execute("undo", &[]);
+        assert_eq!(engine.pos.rules, Rules::aga());
+        assert_eq!(engine.pos.komi, 6.5);
+        engine.execute("boardsize", &["13"]);
+        assert_eq!(engine.pos.rules, Rules::aga());
+    }
+
+    #[test]
+    fn test_gogui_rules_commands() {
+        let mut engine = GtpEngine::new();
+        assert_eq!(engine.execute("gogui-rules_game_id", &[]).1, "Go");
+        assert_eq!(engine.execute("gogui-rules_board_size", &[]).1, "9");
+        assert_eq!(engine.execute("gogui-rules_side_to_move", &[]).1, "black");
+
+        let (success, moves) = engine.execute("gogui-rules_legal_moves", &[]);
+        assert!(success);
+        assert_eq!(moves.split(' ').count(), 81 + 1);
+
+        // White to move: A1 would be suicide
+        play_all(
+            &mut engine,
+            &[("black", "A2"), ("white", "E5"), ("black", "B1")],
+        );
+        assert_eq!(engine.execute("gogui-rules_side_to_move", &[]).1, "white");
+        let (_, moves) = engine.execute("gogui-rules_legal_moves", &[]);
+        assert!(!moves.split(' ').any(|m| m == "A1"));
+        assert!(moves.split(' ').any(|m| m == "pass"));
+
+        // Area count of the board as it stands: 2 + 1 (A1 eye) - 1 - 7.5
+        let (success, result) = engine.execute("gogui-rules_final_result", &[]);
+        assert!(success);
+        assert_eq!(result, "W+5.5");
+    }
+
+    /// Black wall on column E, White wall on column F, and a White stone at
+    /// C5 in atari inside Black's area. Black to play.
+    fn walls_with_dead_stone(engine: &mut GtpEngine) {
+        for row in 1..=9 {
+            engine.execute("play", &["black", &format!("E{row}")]);
+            engine.execute("play", &["white", &format!("F{row}")]);
+        }
+        for vertex in ["C4", "C6", "B5"] {
+            engine.execute("play", &["black", vertex]);
+        }
+        assert!(engine.execute("play", &["white", "C5"]).0);
+    }
+
+    #[test]
+    fn test_final_status_list() {
+        let mut engine = GtpEngine::with_simulations(100);
+        walls_with_dead_stone(&mut engine);
+
+        let (success, dead) = engine.execute("final_status_list", &["dead"]);
+        assert!(success);
+        assert_eq!(dead, "C5");
+
+        let (_, alive) = engine.execute("final_status_list", &["alive"]);
+        let alive: Vec<&str> = alive.split_whitespace().collect();
+        assert!(alive.contains(&"E5") && alive.contains(&"F5") && alive.contains(&"B5"));
+        assert!(!alive.contains(&"C5"));
+
+        assert!(!engine.execute("final_status_list", &["unknown"]).0);
+    }
+
+    #[test]
+    fn test_final_score() {
+        let mut engine = GtpEngine::with_simulations(100);
+        walls_with_dead_stone(&mut engine);
+
+        // Area: 45 points for Black once C5 is removed, 36 for White
+        let (success, result) = engine.execute("final_score", &[]);
+        assert!(success);
+        assert_eq!(result, "B+1.5");
+
+        // Territory scoring: 33 + 1 prisoner for Black, 27 for White
+        engine.execute("kgs-rules", &["japanese"]);
+        assert_eq!(engine.execute("final_score", &[]).1, "W+0.5");
+    }
+
+    #[test]
+    fn test_loadsgf_and_printsgf() {
+        let dir = std::env::temp_dir().join(format!("michi-gtp-sgf-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("game.sgf");
+        std::fs::write(
+            &file,
+            "(;SZ[13]RU[Japanese];B[dj];W[jd];B[jj](;W[dd])(;W[cc]))",
+        )
+        .unwrap();
+        let file = file.to_str().unwrap();
+
+        let mut engine = GtpEngine::new();
+        engine.execute("komi", &["5.5"]);
+        assert!(engine.execute("loadsgf", &[file]).0);
+        assert_eq!(engine.pos.size, 13);
+        assert_eq!(engine.pos.n, 4);
+        assert_eq!(engine.pos.last, parse_coord("D10"));
+        assert_eq!(engine.pos.komi, 5.5, "komi not in the file is kept");
+        assert_eq!(engine.pos.rules, Rules::japanese());
+
+        // Position before move 3, with the earlier moves undoable
+        assert!(engine.execute("loadsgf", &[file, "3"]).0);
+        assert_eq!(engine.pos.n, 2);
+        assert!(engine.execute("undo", &[]).0);
+        assert_eq!(engine.pos.last, parse_coord("D4"));
+
+        engine.execute("play", &["white", "K10"]);
+        let (success, sgf) = engine.execute("printsgf", &[]);
+        assert!(success);
+        assert!(
+            sgf.contains("SZ[13]KM[5.5]RU[Japanese];B[dj];W[jd])"),
+            "{sgf}"
+        );
+
+        let out = dir.join("out.sgf");
+        assert!(engine.execute("printsgf", &[out.to_str().unwrap()]).0);
+        assert_eq!(std::fs::read_to_string(&out).unwrap().trim_end(), sgf);
+
+        assert!(!engine.execute("loadsgf", &["/nonexistent/game.sgf"]).0);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
@@ -0,0 +1,180 @@
+//! Handicap stone placement.
+//!
+//! - [`fixed_handicap_points`] - The standard star-point layouts of the GTP
+//!   specification (`fixed_handicap`)
+//! - [`free_handicap_points`] - A layout chosen by the engine for any number
+//!   of stones (`place_free_handicap`)
+//! - [`place_handicap`] - Put the stones on an empty board and hand the move
+//!   to White
+//!
+//! The handicap is recorded in [`Position::handicap`], from which
+//! [`crate::rules::Rules`] derives White's compensation when scoring.
+
+use crate::constants::{EMPTY, W};
+use crate::position::{Point, Position, line_height, place_stone, set_to_play};
+
+/// Most stones of the fixed layouts on a board of `size`: 9 on odd boards
+/// from 9x9, 4 on 7x7 and even boards, none below 7x7.
+pub fn max_fixed_handicap(size: usize) -> usize {
+    match size {
+        0..7 => 0,
+        7 => 4,
+        _ if size.is_multiple_of(2) => 4,
+        _ => 9,
+    }
+}
+
+/// The fixed handicap layout of `n` stones on a board of `size`, in the
+/// order of the GTP specification, or `None` if there is none.
+///
+/// The corner stones are on the 3rd line up to 12x12 and on the 4th line
+/// from 13x13; the side and center stones are on the middle lines.
+pub fn fixed_handicap_points(size: usize, n: usize) -> Option<Vec<Point>> {
+    if n < 2 || n > max_fixed_handicap(size) {
+        return None;
+    }
+    let lo = if size >= 13 { 4 } else { 3 };
+    let hi = size + 1 - lo;
+    let mid = size.div_ceil(2);
+    let at = |col: usize, row: usize| row * W + col;
+
+    let mut points = vec![at(lo, lo), at(hi, hi), at(lo, hi), at(hi, lo)];
+    points.truncate(n);
+    if n >= 6 {
+        points.extend([at(lo, mid), at(hi, mid)]);
+    }
+    if n >= 8 {
+        points.extend([at(mid, lo), at(mid, hi)]);
+    }
+    if n >= 5 && n % 2 == 1 {
+        points.push(at(mid, mid));
+    }
+    Some(points)
+}
+
+/// A layout of `n` handicap stones chosen by the engine, for any board.
+///
+/// The fixed layout is used as far as it goes; each further stone goes on
+/// the empty point highest from the edges (up to the 3rd line), and among
+/// those the farthest from the stones already placed. Returns fewer than
+/// `n` points only when the board cannot hold them.
+pub fn free_handicap_points(pos: &Position, n: usize) -> Vec<Point> {
+    let fixed = n.min(max_fixed_handicap(pos.size));
+    let mut points = fixed_handicap_points(pos.size, fixed).unwrap_or_default();
+    while points.len() < n {
+        let distance = |pt: Point| {
+            points
+                .iter()
+                .map(|&p| (p / W).abs_diff(pt / W).max((p % W).abs_diff(pt % W)))
+                .min()
+                .unwrap_or(usize::MAX)
+        };
+        let best = (0..pos.imax())
+            .filter(|&pt| pos.color[pt] == EMPTY && !points.contains(&pt))
+            .max_by_key(|&pt| (line_height(pos, pt).min(2), distance(pt), usize::MAX - pt));
+        match best {
+            Some(pt) => points.push(pt),
+            None => break,
+        }
+    }
+    points
+}
+
+/// Set up a handicap game: Black's stones on `points` of the empty board
+/// `pos`, then White to move.
+///
+/// The number of stones is stored in `pos.handicap` for the handicap komi.
+/// White's turn is reached through an implicit pass, as for any move out of
+/// turn (see [`set_to_play`]).
+pub fn place_handicap(pos: &mut Position, points: &[Point]) {
+    for &pt in points {
+        place_stone(pos, pt, true);
+    }
+    pos.handicap = points.len();
+    set_to_play(pos, false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::STONE_WHITE;
+    use crate::playout::score;
+    use crate::position::{parse_coord, str_coord};
+
+    fn coords(points: &[Point]) -> Vec<String> {
+        points.iter().map(|&pt| str_coord(pt)).collect()
+    }
+
+    #[test]
+    fn test_fixed_handicap_points() {
+        assert_eq!(
+            coords(&fixed_handicap_points(19, 9).unwrap()),
+            ["D4", "Q16", "D16", "Q4", "D10", "Q10", "K4", "K16", "K10"]
+        );
+        assert_eq!(
+            coords(&fixed_handicap_points(19, 2).unwrap()),
+            ["D4", "Q16"]
+        );
+        assert_eq!(
+            coords(&fixed_handicap_points(19, 5).unwrap()),
+            ["D4", "Q16", "D16", "Q4", "K10"]
+        );
+        assert_eq!(
+            coords(&fixed_handicap_points(9, 3).unwrap()),
+            ["C3", "G7", "C7"]
+        );
+        assert_eq!(
+            coords(&fixed_handicap_points(13, 6).unwrap()),
+            ["D4", "K10", "D10", "K4", "D7", "K7"]
+        );
+
+        assert_eq!(fixed_handicap_points(19, 1), None);
+        assert_eq!(fixed_handicap_points(19, 10), None);
+        assert_eq!(fixed_handicap_points(10, 5), None);
+        assert_eq!(fixed_handicap_points(7, 4).unwrap().len(), 4);
+        assert_eq!(fixed_handicap_points(5, 2), None);
+    }
+
+    #[test]
+    fn test_free_handicap_points() {
+        let pos = Position::with_size(19);
+        assert_eq!(
+            free_handicap_points(&pos, 4),
+            fixed_handicap_points(19, 4).unwrap()
+        );
+
+        for (size, n) in [(19, 13), (5, 4), (9, 40)] {
+            let pos = Position::with_size(size);
+            let points = free_handicap_points(&pos, n);
+            assert_eq!(points.len(), n);
+            for (i, &pt) in points.iter().enumerate() {
+                assert!(pos.contains(pt));
+                assert!(!points[..i].contains(&pt));
+            }
+        }
+        // The 5x5 layout starts at the center
+        assert_eq!(
+            free_handicap_points(&Position::with_size(5), 1)[0],
+            3 * W + 3
+        );
+    }
+
+    #[test]
+    fn test_place_handicap() {
+        let mut pos = Position::new();
+        pos.komi = 0.5;
+        let points = fixed_handicap_points(9, 2).unwrap();
+        place_handicap(&mut pos, &points);
+
+        assert_eq!(pos.handicap, 2);
+        assert!(!pos.is_black_to_play());
+        // White to play: Black's stones are the opponent's
+        assert_eq!(pos.color[parse_coord("C3")], STONE_WHITE);
+        assert_eq!(pos.color[parse_coord("G7")], STONE_WHITE);
+
+        // Under Chinese rules White, to move, gets a point per stone
+        let mut no_komi = pos.clone();
+        no_komi.handicap = 0;
+        assert_eq!(score(&pos), score(&no_komi) + 2.0);
+    }
+}
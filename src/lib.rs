@@ -5,19 +5,29 @@
 //!
 //! ## Modules
 //!
-//! - [`constants`] - Board dimensions and engine parameters
+//! - [`constants`] - Board geometry and engine parameters
+//! - [`config`] - Search parameters tunable at runtime
 //! - [`position`] - Core game logic (board state, moves, captures)
+//! - [`rules`] - Rule sets (scoring, suicide, superko, handicap komi)
+//! - [`sgf`] - SGF game record reading and writing
+//! - [`handicap`] - Handicap stone layouts and setup
 //! - [`mcts`] - Monte Carlo Tree Search with RAVE
+//! - [`tree`] - Compact arena-allocated search tree
+//! - [`timecontrol`] - Time settings and per-move time budgets
 //! - [`playout`] - Random game simulation for position evaluation
 //! - [`patterns`] - Pattern matching (partially implemented)
 //! - [`board`] - Alternative 2D board representation
 //! - [`gtp`] - Go Text Protocol for GUI integration
+//! - [`server`] - GTP over TCP, one game per connection
+//! - [`selfplay`] - Engine-vs-engine games for datasets and regressions
+//! - [`regress`] - GTP regression suites in GoGui's `.tst` format
 //!
 //! ## Example
 //!
 //! ```
 //! use michi_rust::position::{Position, play_move, parse_coord, str_coord, format_position};
-//! use michi_rust::mcts::{TreeNode, tree_search};
+//! use michi_rust::config::SearchConfig;
+//! use michi_rust::mcts::{Tree, tree_search};
 //!
 //! // Create a new game
 //! let mut pos = Position::new();
@@ -32,15 +42,24 @@
 //! let board_str = format_position(&pos);
 //!
 //! // Run MCTS to find the best response
-//! let mut root = TreeNode::new(&pos);
-//! let best = tree_search(&mut root, 100);
-//! println!("Best move: {}", str_coord(best));
+//! let mut tree = Tree::new(&pos);
+//! let result = tree_search(&mut tree, 100, &SearchConfig::default());
+//! println!("Best move: {} ({:.1}%)", str_coord(result.best_move), result.winrate * 100.0);
 //! ```
 
 pub mod board;
+pub mod config;
 pub mod constants;
 pub mod gtp;
+pub mod handicap;
 pub mod mcts;
 pub mod patterns;
 pub mod playout;
 pub mod position;
+pub mod regress;
+pub mod rules;
+pub mod selfplay;
+pub mod server;
+pub mod sgf;
+pub mod timecontrol;
+pub mod tree;
@@ -9,17 +9,30 @@
 //! - `michi-rust gtp` - Start GTP server for GUI integration
 //! - `michi-rust demo` - Run the MCTS demo
 //! - `michi-rust gtp --patterns michi-c` - Load patterns from michi-c folder
+//! - `michi-rust gtp --threads 8` - Search with 8 threads
+//! - `michi-rust gtp --ponder` - Search during the opponent's turn too
+//! - `michi-rust gtp --config tune.cfg --param rave_equiv=2000` - Set search parameters
+//! - `michi-rust gtp --seed 42` - Seed the random number generator to replay a session
+//! - `michi-rust gtp --listen 127.0.0.1:4242` - Serve GTP over TCP, one game per connection
+//! - `michi-rust regress tests/data/fix_atari.tst` - Run GTP regression suites
+//! - `michi-rust selfplay --games 10 --sims 500 --out games/` - Write self-play games as SGF
 
-use std::path::PathBuf;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand, ValueEnum};
 
 use michi_rust::board::{Board, Color};
+use michi_rust::config::SearchConfig;
+use michi_rust::constants::{DEFAULT_N, MAX_N, MIN_N, TREE_MEMORY_MB};
 use michi_rust::gtp::GtpEngine;
-use michi_rust::mcts::TreeNode;
+use michi_rust::mcts::Tree;
 use michi_rust::patterns::{load_large_patterns, load_large_patterns_from};
 use michi_rust::position::{Position, str_coord};
+use michi_rust::regress::{self, Outcome, Summary};
+use michi_rust::selfplay::play_game;
+use michi_rust::server;
 
 /// Predefined intelligence levels
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -72,6 +85,95 @@ enum Commands {
         /// Directory containing patterns.prob and patterns.spat files
         #[arg(short = 'p', long)]
         patterns: Option<PathBuf>,
+
+        /// Number of search threads (root parallelism)
+        #[arg(short = 't', long, default_value_t = 1)]
+        threads: usize,
+
+        /// Keep searching while waiting for the opponent's move
+        #[arg(long)]
+        ponder: bool,
+
+        /// Memory cap of the search tree, in MiB
+        #[arg(long, default_value_t = TREE_MEMORY_MB)]
+        tree_memory: usize,
+
+        /// File of search parameters, one `name = value` per line
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Set a search parameter (repeatable; applied after --config)
+        #[arg(long = "param", value_name = "NAME=VALUE")]
+        params: Vec<String>,
+
+        /// Seed of the random number generator (random by default)
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Serve GTP over TCP on this address (e.g. 127.0.0.1:4242) instead
+        /// of stdin/stdout, with a separate game per connection
+        #[arg(long, value_name = "ADDR:PORT")]
+        listen: Option<String>,
+    },
+    /// Run GTP regression suites (.tst files in GoGui's format)
+    Regress {
+        /// Suite files
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Number of MCTS simulations per move
+        #[arg(short = 's', long, default_value_t = 1400)]
+        simulations: usize,
+
+        /// Board size each suite starts on (michi-c's suites assume 13)
+        #[arg(long, default_value_t = 13)]
+        boardsize: usize,
+
+        /// Directory containing patterns.prob and patterns.spat files
+        #[arg(short = 'p', long)]
+        patterns: Option<PathBuf>,
+
+        /// Seed of the random number generator (random by default)
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Play engine-vs-engine games and write them as SGF files
+    Selfplay {
+        /// Number of games
+        #[arg(short = 'g', long, default_value_t = 1)]
+        games: usize,
+
+        /// Number of MCTS simulations per move
+        #[arg(short = 's', long, default_value_t = 1400)]
+        sims: usize,
+
+        /// Directory for the SGF files (created if missing)
+        #[arg(short = 'o', long)]
+        out: PathBuf,
+
+        /// Board size
+        #[arg(long, default_value_t = DEFAULT_N)]
+        boardsize: usize,
+
+        /// Komi
+        #[arg(long, default_value_t = 7.5)]
+        komi: f32,
+
+        /// Directory containing patterns.prob and patterns.spat files
+        #[arg(short = 'p', long)]
+        patterns: Option<PathBuf>,
+
+        /// File of search parameters, one `name = value` per line
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Set a search parameter (repeatable; applied after --config)
+        #[arg(long = "param", value_name = "NAME=VALUE")]
+        params: Vec<String>,
+
+        /// Seed of the random number generator (random by default)
+        #[arg(long)]
+        seed: Option<u64>,
     },
     /// Run a simple demo of the engine
     Demo {
@@ -89,10 +191,25 @@ fn main() {
             simulations,
             level,
             patterns,
+            threads,
+            ponder,
+            tree_memory,
+            config,
+            params,
+            seed,
+            listen,
         }) => {
             // Load patterns if specified
             load_patterns_from_arg(&patterns);
 
+            let config = match load_config(config.as_deref(), &params) {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("michi-rust: {:#}", e);
+                    std::process::exit(1);
+                }
+            };
+
             // Determine number of simulations
             let n_sims = if let Some(lvl) = level {
                 lvl.to_sims()
@@ -101,17 +218,77 @@ fn main() {
             };
 
             eprintln!(
-                "michi-rust: Starting GTP with {} simulations per move",
-                n_sims
+                "michi-rust: Starting GTP with {} simulations per move on {} thread(s)",
+                n_sims, threads
             );
 
             // Run GTP server
-            let mut engine = GtpEngine::with_simulations(n_sims);
-            if let Err(e) = engine.run() {
+            let new_engine = move || {
+                let engine = GtpEngine::with_simulations(n_sims)
+                    .with_threads(threads)
+                    .with_ponder(ponder)
+                    .with_tree_memory(tree_memory)
+                    .with_config(config.clone());
+                match seed {
+                    Some(seed) => engine.with_seed(seed),
+                    None => engine,
+                }
+            };
+            if let Some(addr) = listen {
+                let listener = match TcpListener::bind(&addr) {
+                    Ok(listener) => listener,
+                    Err(e) => {
+                        eprintln!("michi-rust: cannot listen on {addr}: {e}");
+                        std::process::exit(1);
+                    }
+                };
+                if let Ok(local) = listener.local_addr() {
+                    eprintln!("michi-rust: Listening for GTP connections on {local}");
+                }
+                // The boards and search logs of concurrent games would be
+                // interleaved on stderr, so the engines keep quiet
+                server::serve(listener, move || new_engine().without_diagnostics());
+            } else if let Err(e) = new_engine().run() {
                 eprintln!("GTP error: {}", e);
                 std::process::exit(1);
             }
         }
+        Some(Commands::Regress {
+            files,
+            simulations,
+            boardsize,
+            patterns,
+            seed,
+        }) => {
+            load_patterns_from_arg(&patterns);
+            match run_regress(&files, simulations, boardsize, seed) {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("michi-rust: {:#}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Selfplay {
+            games,
+            sims,
+            out,
+            boardsize,
+            komi,
+            patterns,
+            config,
+            params,
+            seed,
+        }) => {
+            load_patterns_from_arg(&patterns);
+            let result = load_config(config.as_deref(), &params)
+                .and_then(|config| run_selfplay(games, sims, &out, boardsize, komi, &config, seed));
+            if let Err(e) = result {
+                eprintln!("michi-rust: {:#}", e);
+                std::process::exit(1);
+            }
+        }
         Some(Commands::Demo { patterns }) => {
             load_patterns_from_arg(&patterns);
             if let Err(e) = run_demo() {
@@ -148,6 +325,102 @@ fn load_patterns_from_arg(patterns: &Option<PathBuf>) {
     }
 }
 
+/// Search parameters from the defaults, a config file and `name=value` overrides.
+fn load_config(file: Option<&Path>, params: &[String]) -> Result<SearchConfig> {
+    let mut config = SearchConfig::default();
+    if let Some(file) = file {
+        config.load(file)?;
+    }
+    for param in params {
+        config.set_assignment(param)?;
+    }
+    Ok(config)
+}
+
+/// Run each suite on a fresh engine, printing every case and a summary.
+/// Returns whether all cases passed or failed as expected.
+fn run_regress(
+    files: &[PathBuf],
+    simulations: usize,
+    boardsize: usize,
+    seed: Option<u64>,
+) -> Result<bool> {
+    let mut total = Summary::default();
+    for file in files {
+        let mut engine = GtpEngine::with_simulations(simulations);
+        if let Some(seed) = seed {
+            engine = engine.with_seed(seed);
+        }
+        let (success, response) = engine.execute_line(&format!("boardsize {boardsize}"));
+        if !success {
+            anyhow::bail!("boardsize {boardsize}: {response}");
+        }
+
+        println!("{}:", file.display());
+        let results = regress::run_file(&mut engine, file)?;
+        for case in &results {
+            println!("  {} {}", case.id, case.outcome);
+            if let Some(failure) = &case.setup_failure {
+                println!("      setup failed at {failure}");
+            }
+            if matches!(case.outcome, Outcome::Fail | Outcome::UnexpectedPass) {
+                println!("      line {}: {}", case.line, case.command);
+                println!("      got [{}], expected {}", case.response, case.expected);
+            }
+        }
+        let summary = Summary::of(&results);
+        println!("  {summary}");
+        total.add(summary);
+    }
+    if files.len() > 1 {
+        println!("Total: {total}");
+    }
+    Ok(total.ok())
+}
+
+/// Play `games` games, writing each to `out` as `game-NNNN.sgf`, and print
+/// every result and the tally.
+fn run_selfplay(
+    games: usize,
+    sims: usize,
+    out: &Path,
+    boardsize: usize,
+    komi: f32,
+    config: &SearchConfig,
+    seed: Option<u64>,
+) -> Result<()> {
+    if !(MIN_N..=MAX_N).contains(&boardsize) {
+        anyhow::bail!("board size must be between {MIN_N} and {MAX_N}");
+    }
+    std::fs::create_dir_all(out).with_context(|| format!("cannot create {}", out.display()))?;
+    let seed = seed.unwrap_or_else(|| fastrand::u64(..));
+    println!("Self-play: {games} game(s), {sims} simulations per move, seed {seed}");
+
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let mut start = Position::with_size(boardsize);
+    start.komi = komi;
+    let (mut black_wins, mut white_wins) = (0, 0);
+    for i in 1..=games {
+        let record = play_game(&start, sims, config, &mut rng);
+        let path = out.join(format!("game-{i:04}.sgf"));
+        std::fs::write(&path, record.to_sgf())
+            .with_context(|| format!("cannot write {}", path.display()))?;
+        match record.result.chars().next() {
+            Some('B') => black_wins += 1,
+            Some('W') => white_wins += 1,
+            _ => {}
+        }
+        println!(
+            "game {i}: {} in {} moves -> {}",
+            record.result,
+            record.moves(),
+            path.display()
+        );
+    }
+    println!("Black won {black_wins}, White won {white_wins} of {games}");
+    Ok(())
+}
+
 fn run_demo() -> Result<()> {
     println!("Michi-Rust: Minimalistic Go MCTS Engine\n");
 
@@ -174,11 +447,19 @@ fn run_demo() -> Result<()> {
     println!("{pos}");
 
     // Run MCTS
-    let mut root = TreeNode::new(&pos);
+    let mut tree = Tree::new(&pos);
     println!("Running 100 MCTS simulations...");
-    let best_move = michi_rust::mcts::tree_search(&mut root, 100);
-    println!("Best move: {}", str_coord(best_move));
-    println!("Root winrate: {:.1}%", root.winrate() * 100.0);
+    let result = michi_rust::mcts::tree_search(&mut tree, 100, &SearchConfig::default());
+    println!("Best move: {}", str_coord(result.best_move));
+    println!("Winrate: {:.1}%", result.winrate * 100.0);
+    let pv: Vec<String> = result.pv().iter().map(|&mv| str_coord(mv)).collect();
+    println!("Principal variation: {}", pv.join(" "));
+    println!(
+        "{} simulations in {:.2}s ({:?})",
+        result.sims,
+        result.elapsed.as_secs_f64(),
+        result.stop
+    );
 
     Ok(())
 }
@@ -5,89 +5,57 @@
 //! - Progressive widening for tree expansion
 //! - Pattern-based priors for move prioritization
 //! - Simple random playouts for value estimation
+//! - Optional root parallelism over several threads
+//! - Progress reporting through a [`SearchObserver`]
+//! - Early stops at a deadline or through a [`CancelToken`] ([`SearchLimits`])
 //!
-//! The search maintains a tree where each node represents a game position.
-//! The tree is expanded incrementally, and leaf nodes are evaluated using playouts.
+//! The search maintains a [`Tree`] where each node represents a game position,
+//! stored compactly as the move leading to it (see [`crate::tree`]). The tree
+//! is expanded incrementally, and leaf nodes are evaluated using playouts.
 
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use fastrand::Rng;
+
+use crate::config::SearchConfig;
 use crate::constants::{
-    BOARD_IMAX, BOARD_IMIN, BOARDSIZE, EMPTY, EXPAND_VISITS, N, OUT, PASS_MOVE, PRIOR_CAPTURE_MANY,
-    PRIOR_CAPTURE_ONE, PRIOR_CFG, PRIOR_EMPTYAREA, PRIOR_EVEN, PRIOR_LARGEPATTERN, PRIOR_PAT3,
-    PRIOR_SELFATARI, RAVE_EQUIV, W,
+    BOARD_IMIN, BOARDSIZE, EMPTY, OUT, PASS_MOVE, PV_LEN, STATUS_THRES, STONE_BLACK, STONE_WHITE,
+    UNCLEAR_LEAD,
 };
 use crate::patterns::{large_pattern_probability, pat3_match};
 use crate::playout::mcplayout;
 use crate::position::{
-    Point, Position, all_neighbors, fix_atari_ext, gen_capture_moves_all, is_eye, is_eyeish,
-    pass_move, play_move, str_coord,
+    Point, Position, all_neighbors, compute_block, fix_atari_ext, gen_capture_moves_all, is_eye,
+    is_eyeish, line_height, play_move, str_coord,
 };
-
-/// A node in the MCTS search tree.
-///
-/// Each node stores statistics for both regular visits (v, w) and AMAF visits (av, aw),
-/// as well as prior values (pv, pw) for initialization.
-pub struct TreeNode {
-    /// The game position at this node
-    pub pos: Position,
-    /// Number of visits
-    pub v: u32,
-    /// Number of wins (winrate = w/v)
-    pub w: u32,
-    /// Prior visits (for initialization)
-    pub pv: u32,
-    /// Prior wins
-    pub pw: u32,
-    /// AMAF (All Moves As First) visits
-    pub av: u32,
-    /// AMAF wins
-    pub aw: u32,
-    /// Child nodes (one per legal move)
-    pub children: Vec<TreeNode>,
-}
-
-impl TreeNode {
-    /// Create a new tree node for the given position.
-    pub fn new(pos: &Position) -> Self {
-        Self {
-            pos: pos.clone(),
-            v: 0,
-            w: 0,
-            pv: PRIOR_EVEN,
-            pw: PRIOR_EVEN / 2,
-            av: 0,
-            aw: 0,
-            children: Vec::new(),
-        }
-    }
-
-    /// Calculate the winrate for this node.
-    #[inline]
-    pub fn winrate(&self) -> f64 {
-        if self.v > 0 {
-            self.w as f64 / self.v as f64
-        } else {
-            -0.1 // Indicate unvisited
-        }
-    }
-}
+use crate::timecontrol::Deadline;
+pub use crate::tree::{NodeId, Tree, TreeNode};
 
 /// Expand a node by generating all legal child moves.
 ///
-/// Each legal move becomes a child node. If no moves are available,
-/// a pass move is added.
+/// `pos` is the position at the node. Each legal move becomes a child node.
+/// If no moves are available, a pass move is added. Nodes other than the
+/// root are left unexpanded once the tree's node cap is reached.
 ///
-/// Applies priors based on:
-/// - Capture moves (PRIOR_CAPTURE_ONE, PRIOR_CAPTURE_MANY)
-/// - 3x3 patterns (PRIOR_PAT3)
-/// - CFG distance from last move (PRIOR_CFG)
-/// - Self-atari detection (PRIOR_SELFATARI as negative prior)
-pub fn expand(node: &mut TreeNode) {
-    if !node.children.is_empty() {
+/// Applies priors from `config` based on:
+/// - Capture moves (prior_capture_one, prior_capture_many)
+/// - 3x3 patterns (prior_pat3)
+/// - CFG distance from last move (prior_cfg)
+/// - Self-atari detection (prior_selfatari as negative prior)
+pub fn expand(tree: &mut Tree, id: NodeId, pos: &Position, config: &SearchConfig) {
+    if tree.node(id).is_expanded() {
+        return;
+    }
+    if id != Tree::ROOT && !tree.has_room(pos.size * pos.size + 1) {
         return;
     }
 
     // Compute CFG distances from last move
-    let cfg_map = if node.pos.last != PASS_MOVE {
-        Some(compute_cfg_distances(&node.pos, node.pos.last))
+    let cfg_map = if pos.last != PASS_MOVE {
+        Some(compute_cfg_distances(pos, pos.last))
     } else {
         None
     };
@@ -95,34 +63,70 @@ pub fn expand(node: &mut TreeNode) {
     // Precompute capture moves for priors
     // Use gen_capture_moves_all to scan ALL groups on the board (not just neighbors)
     // with twolib_edgeonly=false for full ladder analysis (expensive but accurate for priors)
-    let capture_moves = gen_capture_moves_all(&node.pos, false);
+    let capture_moves = gen_capture_moves_all(pos, false);
 
-    // Generate all legal moves
-    for pt in BOARD_IMIN..BOARD_IMAX {
-        if node.pos.color[pt] != b'.' {
+    // Generate all legal moves, trying each on one scratch position
+    let mut children = Vec::new();
+    let mut child_pos = pos.clone();
+    for pt in BOARD_IMIN..pos.imax() {
+        if pos.color[pt] != b'.' {
             continue;
         }
         // Skip true eyes for current player (never a good move)
-        if is_eye(&node.pos, pt) == b'X' {
+        if is_eye(pos, pt) == b'X' {
             continue;
         }
 
-        let mut child_pos = node.pos.clone();
-        if play_move(&mut child_pos, pt).is_ok() {
-            let mut child = TreeNode::new(&child_pos);
+        child_pos.clone_from(pos);
+        // Suicide (when the rules allow it) leaves pt empty and is skipped
+        if play_move(&mut child_pos, pt).is_ok() && child_pos.color[pt] != EMPTY {
+            let mut child = TreeNode::with_prior(pt, config.prior_even);
 
             // Apply priors
-            apply_priors(&mut child, &node.pos, pt, &cfg_map, &capture_moves);
-
-            node.children.push(child);
+            apply_priors(
+                &mut child,
+                pos,
+                &child_pos,
+                pt,
+                &cfg_map,
+                &capture_moves,
+                config,
+            );
+
+            children.push(child);
         }
     }
 
     // Always allow passing if no other moves
-    if node.children.is_empty() {
-        let mut child_pos = node.pos.clone();
-        pass_move(&mut child_pos);
-        node.children.push(TreeNode::new(&child_pos));
+    if children.is_empty() {
+        children.push(TreeNode::with_prior(PASS_MOVE, config.prior_even));
+    }
+    tree.add_children(id, &children);
+}
+
+/// Expand the root and drop the children whose positions `allowed` rejects.
+///
+/// `expand` only sees the position at each node, so rules that depend on the
+/// game history (superko) are applied here, at the root. Pass is always kept,
+/// and added if no other move is left.
+pub fn expand_root_with(
+    tree: &mut Tree,
+    config: &SearchConfig,
+    allowed: impl Fn(&Position) -> bool,
+) {
+    let pos = tree.pos().clone();
+    expand(tree, Tree::ROOT, &pos, config);
+    let mut child_pos = pos.clone();
+    tree.retain_children(Tree::ROOT, |c| {
+        child_pos.clone_from(&pos);
+        Tree::replay(&mut child_pos, c.mv());
+        c.mv() == PASS_MOVE || allowed(&child_pos)
+    });
+    if !tree.root().is_expanded() {
+        tree.add_children(
+            Tree::ROOT,
+            &[TreeNode::with_prior(PASS_MOVE, config.prior_even)],
+        );
     }
 }
 
@@ -130,15 +134,17 @@ pub fn expand(node: &mut TreeNode) {
 fn apply_priors(
     child: &mut TreeNode,
     parent_pos: &Position,
+    child_pos: &Position,
     pt: Point,
     cfg_map: &Option<[i8; BOARDSIZE]>,
     capture_moves: &[(Point, usize)],
+    config: &SearchConfig,
 ) {
     // 1. CFG distance prior - moves near the last move get a bonus
     if let Some(cfg) = cfg_map {
         let dist = cfg[pt];
-        if dist >= 1 && (dist as usize) <= PRIOR_CFG.len() {
-            let bonus = PRIOR_CFG[(dist - 1) as usize];
+        if dist >= 1 && (dist as usize) <= config.prior_cfg.len() {
+            let bonus = config.prior_cfg[(dist - 1) as usize];
             child.pv += bonus;
             child.pw += bonus;
         }
@@ -146,8 +152,8 @@ fn apply_priors(
 
     // 2. 3x3 pattern prior
     if pat3_match(parent_pos, pt) {
-        child.pv += PRIOR_PAT3;
-        child.pw += PRIOR_PAT3;
+        child.pv += config.prior_pat3;
+        child.pw += config.prior_pat3;
     }
 
     // 3. Large pattern prior - use probability from pattern database
@@ -155,8 +161,8 @@ fn apply_priors(
     if pattern_prob > 0.0 {
         // Apply sqrt() to "tone up" low-probability patterns (same as michi-c)
         let pattern_prior = pattern_prob.sqrt() as u32;
-        child.pv += pattern_prior * PRIOR_LARGEPATTERN;
-        child.pw += pattern_prior * PRIOR_LARGEPATTERN;
+        child.pv += pattern_prior * config.prior_largepattern;
+        child.pw += pattern_prior * config.prior_largepattern;
     }
 
     // 4. Capture prior - check if this move captures or saves stones
@@ -164,11 +170,11 @@ fn apply_priors(
     for &(mv, size) in capture_moves {
         if mv == pt {
             if size == 1 {
-                child.pv += PRIOR_CAPTURE_ONE;
-                child.pw += PRIOR_CAPTURE_ONE;
+                child.pv += config.prior_capture_one;
+                child.pw += config.prior_capture_one;
             } else {
-                child.pv += PRIOR_CAPTURE_MANY;
-                child.pw += PRIOR_CAPTURE_MANY;
+                child.pv += config.prior_capture_many;
+                child.pw += config.prior_capture_many;
             }
             break;
         }
@@ -179,19 +185,19 @@ fn apply_priors(
     // - singlept_ok=true (SINGLEPT_OK): don't worry about single stone groups
     // - twolib_test=true (TWOLIBS_TEST): check 2-lib groups for ladder captures
     // - twolib_edgeonly=false (!TWOLIBS_EDGE_ONLY): full ladder analysis (expensive but accurate)
-    let atari_moves = fix_atari_ext(&child.pos, pt, true, true, false);
+    let atari_moves = fix_atari_ext(child_pos, pt, true, true, false);
     if !atari_moves.is_empty() {
-        child.pv += PRIOR_SELFATARI;
+        child.pv += config.prior_selfatari;
         // pw stays at pw, giving a lower winrate
     }
 
     // 6. Empty area prior - penalize moves on 1st/2nd line with no stones nearby
-    let height = line_height(pt);
+    let height = line_height(parent_pos, pt);
     if height <= 2 && empty_area(parent_pos, pt, 3) {
-        child.pv += PRIOR_EMPTYAREA;
+        child.pv += config.prior_emptyarea;
         if height == 2 {
             // 3rd line is OK in empty areas
-            child.pw += PRIOR_EMPTYAREA;
+            child.pw += config.prior_emptyarea;
         }
         // 1st/2nd line in empty area gets no pw bonus (negative prior)
     }
@@ -237,18 +243,6 @@ fn compute_cfg_distances(pos: &Position, start: Point) -> [i8; BOARDSIZE] {
     cfg_map
 }
 
-/// Return the line number (0-indexed) from nearest board edge.
-fn line_height(pt: Point) -> usize {
-    let row = pt / W;
-    let col = pt % W;
-
-    // Distance from edges
-    let row_dist = row.min(N + 1 - row);
-    let col_dist = col.min(N + 1 - col);
-
-    row_dist.min(col_dist).saturating_sub(1)
-}
-
 /// Check if there are no stones within Manhattan distance `dist` of point.
 fn empty_area(pos: &Position, pt: Point, dist: usize) -> bool {
     if dist == 0 {
@@ -272,8 +266,8 @@ fn empty_area(pos: &Position, pt: Point, dist: usize) -> bool {
 ///
 /// Combines the node's empirical winrate with AMAF (All Moves As First) statistics.
 /// The balance between empirical and AMAF is controlled by the beta parameter,
-/// which decreases as the node gets more visits.
-fn rave_urgency(node: &TreeNode) -> f64 {
+/// which decreases as the node gets more visits, at a rate set by `rave_equiv`.
+fn rave_urgency(node: &TreeNode, rave_equiv: usize) -> f64 {
     let v = (node.v + node.pv) as f64;
     let expectation = (node.w + node.pw) as f64 / v;
 
@@ -282,7 +276,7 @@ fn rave_urgency(node: &TreeNode) -> f64 {
     }
 
     let rave_expectation = node.aw as f64 / node.av as f64;
-    let beta = node.av as f64 / (node.av as f64 + v + v * node.av as f64 / RAVE_EQUIV as f64);
+    let beta = node.av as f64 / (node.av as f64 + v + v * node.av as f64 / rave_equiv as f64);
     beta * rave_expectation + (1.0 - beta) * expectation
 }
 
@@ -290,7 +284,7 @@ fn rave_urgency(node: &TreeNode) -> f64 {
 ///
 /// When multiple children have equal urgency (common early in search),
 /// picks one randomly using reservoir sampling to avoid shuffling the array.
-fn most_urgent(children: &[TreeNode]) -> usize {
+fn most_urgent(children: &[TreeNode], rave_equiv: usize, rng: &mut Rng) -> usize {
     if children.is_empty() {
         return 0;
     }
@@ -303,7 +297,7 @@ fn most_urgent(children: &[TreeNode]) -> usize {
     let mut count = 0;
 
     for (i, child) in children.iter().enumerate() {
-        let urgency = rave_urgency(child);
+        let urgency = rave_urgency(child, rave_equiv);
 
         if urgency > best_urgency {
             best_urgency = urgency;
@@ -313,7 +307,7 @@ fn most_urgent(children: &[TreeNode]) -> usize {
             // Equal urgency (within epsilon)
             count += 1;
             // Reservoir sampling: replace with probability 1/count
-            if fastrand::usize(0..count) == 0 {
+            if rng.usize(0..count) == 0 {
                 best_idx = i;
             }
         }
@@ -324,101 +318,73 @@ fn most_urgent(children: &[TreeNode]) -> usize {
 
 /// Descend through the tree to a leaf node, recording the path taken.
 ///
-/// Returns the path of child indices from root to leaf.
+/// Returns the ids of the nodes from the root's child to the leaf, and the
+/// position at the leaf, rebuilt by replaying their moves from the root.
 /// Updates the AMAF map with moves played during descent.
-fn tree_descend(tree: &mut TreeNode, amaf_map: &mut [i8]) -> Vec<usize> {
+fn tree_descend(
+    tree: &mut Tree,
+    amaf_map: &mut [i8],
+    config: &SearchConfig,
+    rng: &mut Rng,
+) -> (Vec<NodeId>, Position) {
+    let mut pos = tree.pos().clone();
     let mut path = Vec::new();
-    let mut node = tree;
+    let mut node = Tree::ROOT;
     let mut passes = 0;
 
-    loop {
-        if node.children.is_empty() || passes >= 2 {
-            break;
-        }
-
-        let child_idx = most_urgent(&node.children);
-        path.push(child_idx);
-
-        let child = &node.children[child_idx];
-        let mv = child.pos.last;
+    while tree.node(node).is_expanded() && passes < 2 {
+        let child = tree.child_ids(node).start
+            + most_urgent(tree.children(node), config.rave_equiv, rng) as NodeId;
+        path.push(child);
 
+        let mv = tree.node(child).mv();
         if mv == PASS_MOVE {
             passes += 1;
         } else {
             passes = 0;
             if amaf_map[mv] == 0 {
                 // Mark with 1 for black, -1 for white
-                amaf_map[mv] = if node.pos.is_black_to_play() { 1 } else { -1 };
+                amaf_map[mv] = if pos.is_black_to_play() { 1 } else { -1 };
             }
         }
+        Tree::replay(&mut pos, mv);
 
         // Expand if this node has enough visits
-        {
-            let child = &mut node.children[child_idx];
-            if child.children.is_empty() && child.v >= EXPAND_VISITS {
-                expand(child);
-            }
+        if tree.node(child).v >= config.expand_visits {
+            expand(tree, child, &pos, config);
         }
 
-        // Move to child (we need to reborrow)
-        node = &mut node.children[child_idx];
+        node = child;
     }
 
-    path
+    (path, pos)
 }
 
 /// Update tree statistics after a playout.
 ///
 /// Propagates the playout result back up the tree, updating visit and win counts.
 /// Also updates AMAF statistics for sibling moves that appeared in the playout.
-fn tree_update(tree: &mut TreeNode, path: &[usize], amaf_map: &[i8], mut score: f64) {
+fn tree_update(tree: &mut Tree, path: &[NodeId], amaf_map: &[i8], mut score: f64) {
     // Adjust score to be relative to the root player
     // mcplayout returns score for the player at the leaf node.
     // If the path length is odd, the leaf player is the opponent of the root player.
-    if path.len() % 2 != 0 {
+    if !path.len().is_multiple_of(2) {
         score = -score;
     }
 
-    // Update root
-    tree.v += 1;
-    if score < 0.0 {
-        tree.w += 1;
-    }
-
-    // Update AMAF for root's children
-    let amaf_value = if tree.pos.is_black_to_play() {
-        1i8
-    } else {
-        -1i8
-    };
-    for child in &mut tree.children {
-        if child.pos.last != 0 && amaf_map[child.pos.last] == amaf_value {
-            child.av += 1;
-            if score > 0.0 {
-                child.aw += 1;
-            }
-        }
-    }
-
-    score = -score;
-
-    // Walk down the path updating nodes
-    let mut node = tree;
-    for &idx in path {
-        node = &mut node.children[idx];
+    // Walk down from the root updating nodes; the side to move alternates
+    let mut black = tree.pos().is_black_to_play();
+    for id in std::iter::once(Tree::ROOT).chain(path.iter().copied()) {
+        let node = tree.node_mut(id);
         node.v += 1;
         if score < 0.0 {
             node.w += 1;
         }
 
         // Update AMAF for this node's children
-        let amaf_value = if node.pos.is_black_to_play() {
-            1i8
-        } else {
-            -1i8
-        };
-        for child in &mut node.children {
-            if child.pos.last != 0 && amaf_map[child.pos.last] == amaf_value {
+        let amaf_value = if black { 1i8 } else { -1i8 };
+        for child in tree.children_mut(id) {
+            if child.mv() != PASS_MOVE && amaf_map[child.mv()] == amaf_value {
                 child.av += 1;
                 if score > 0.0 {
                     child.aw += 1;
@@ -427,80 +393,332 @@ fn tree_update(tree: &mut TreeNode, path: &[usize], amaf_map: &[i8], mut score:
         }
 
         score = -score;
+        black = !black;
     }
 }
 
-/// Get the position at the leaf node reached by following the given path.
-fn get_leaf_position(tree: &TreeNode, path: &[usize]) -> Position {
-    path.iter()
-        .fold(tree, |node, &idx| &node.children[idx])
-        .pos
-        .clone()
+/// Best winrate among the visited children of the root (for early stopping).
+fn best_root_winrate(tree: &Tree) -> f64 {
+    tree.children(Tree::ROOT)
+        .iter()
+        .filter(|c| c.v > 0)
+        .map(|c| c.winrate())
+        .fold(0.0_f64, f64::max)
+}
+
+/// Why a search ended.
+///
+/// The reasons are ordered by precedence, for searches whose threads stop
+/// for different reasons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StopReason {
+    /// All the simulations asked for were run
+    Completed,
+    /// The best move's winrate passed a fast-play threshold
+    FastPlay,
+    /// The time budget ran out
+    Deadline,
+    /// The search was cancelled through its [`CancelToken`]
+    Cancelled,
+}
+
+/// A handle to stop a search from another thread. Clones share one flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// A token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop the searches using this token. Each returns once its current
+    /// simulation is over, with the best move found so far.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether `cancel` was called since the token was created or reset.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Clear the flag, so that the token can stop another search.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// What ends a search early, besides fast play.
+#[derive(Debug, Clone, Default)]
+pub struct SearchLimits {
+    /// Stop at this deadline (see [`out_of_time`])
+    pub deadline: Option<Deadline>,
+    /// Stop once this token is cancelled
+    pub cancel: Option<CancelToken>,
+}
+
+/// Statistics of one root child after a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    /// The move
+    pub mv: Point,
+    /// Visits and wins, for the side to move at the root
+    pub visits: u32,
+    pub wins: u32,
+    /// Prior visits and wins
+    pub prior_visits: u32,
+    pub prior_wins: u32,
+    /// AMAF (RAVE) visits and wins
+    pub rave_visits: u32,
+    pub rave_wins: u32,
+    /// Principal variation, starting with `mv`
+    pub pv: Vec<Point>,
+}
+
+impl Candidate {
+    /// Winrate of the move, or `None` if it was not visited.
+    pub fn winrate(&self) -> Option<f64> {
+        (self.visits > 0).then(|| f64::from(self.wins) / f64::from(self.visits))
+    }
+
+    /// Prior winrate of the move.
+    pub fn prior_winrate(&self) -> f64 {
+        f64::from(self.prior_wins) / f64::from(self.prior_visits.max(1))
+    }
+
+    /// AMAF winrate of the move, or `None` without AMAF visits.
+    pub fn rave_winrate(&self) -> Option<f64> {
+        (self.rave_visits > 0).then(|| f64::from(self.rave_wins) / f64::from(self.rave_visits))
+    }
+}
+
+/// The outcome of a search: the move to play and what the tree says about it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    /// The most visited root child, or pass if there is none
+    pub best_move: Point,
+    /// Winrate of the best move for the side to move (0 if it was not visited)
+    pub winrate: f64,
+    /// Every root child, most visited first
+    pub candidates: Vec<Candidate>,
+    /// Simulations run by this search
+    pub sims: usize,
+    /// Time the search took
+    pub elapsed: Duration,
+    /// Why the search ended
+    pub stop: StopReason,
+}
+
+impl SearchResult {
+    /// The result of a search that ran `sims` simulations on `tree`.
+    pub fn from_tree(tree: &Tree, sims: usize, elapsed: Duration, stop: StopReason) -> Self {
+        let ids = get_best_moves(tree, Tree::ROOT, tree.children(Tree::ROOT).len());
+        let candidates: Vec<Candidate> = ids
+            .into_iter()
+            .map(|id| {
+                let node = tree.node(id);
+                Candidate {
+                    mv: node.mv(),
+                    visits: node.v,
+                    wins: node.w,
+                    prior_visits: node.pv,
+                    prior_wins: node.pw,
+                    rave_visits: node.av,
+                    rave_wins: node.aw,
+                    pv: principal_variation(tree, id, PV_LEN),
+                }
+            })
+            .collect();
+        let best = candidates.first();
+        Self {
+            best_move: best.map_or(PASS_MOVE, |c| c.mv),
+            winrate: best.and_then(Candidate::winrate).unwrap_or(0.0),
+            candidates,
+            sims,
+            elapsed,
+            stop,
+        }
+    }
+
+    /// The principal variation of the best move (empty if there is none).
+    pub fn pv(&self) -> &[Point] {
+        self.candidates.first().map_or(&[], |c| &c.pv)
+    }
+}
+
+/// Receives progress events from a search.
+///
+/// All methods do nothing by default. [`LogObserver`] writes the
+/// progress reports michi-c prints; [`NullObserver`] ignores everything.
+///
+/// A multi-threaded [`tree_search_parallel`] reports the progress of its
+/// first thread only, with that thread's tree and simulation count.
+pub trait SearchObserver {
+    /// Called after each simulation, with the number run so far.
+    fn on_sim(&mut self, _tree: &Tree, _sims: usize) {}
+
+    /// Called every `REPORT_PERIOD` simulations, with the number run so far.
+    fn on_report(&mut self, _tree: &Tree, _sims: usize) {}
+
+    /// Called once when the search is over.
+    fn on_finish(&mut self, _tree: &Tree, _result: &SearchResult) {}
+}
+
+/// An observer that ignores every event.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullObserver;
+
+impl SearchObserver for NullObserver {}
+
+/// An observer that writes a summary line to `out` at every report and
+/// dumps the tree at the end. Write errors are ignored: the log is only
+/// diagnostics and must not disturb the search.
+#[derive(Debug, Clone)]
+pub struct LogObserver<W> {
+    config: SearchConfig,
+    out: W,
+}
+
+impl<W: Write> LogObserver<W> {
+    /// An observer for a search with `config` (used for the urgencies shown)
+    /// writing to `out`.
+    pub fn new(config: &SearchConfig, out: W) -> Self {
+        Self {
+            config: config.clone(),
+            out,
+        }
+    }
+}
+
+impl LogObserver<io::Stderr> {
+    /// An observer writing to stderr, as michi-c does.
+    pub fn stderr(config: &SearchConfig) -> Self {
+        Self::new(config, io::stderr())
+    }
+}
+
+impl<W: Write> SearchObserver for LogObserver<W> {
+    fn on_report(&mut self, tree: &Tree, sims: usize) {
+        let _ = print_tree_summary(&mut self.out, tree, sims);
+    }
+
+    fn on_finish(&mut self, tree: &Tree, result: &SearchResult) {
+        // Only the children with a 50th of the simulations are shown
+        let thres = (result.sims / 50) as u32;
+        let _ = dump_subtree(
+            &mut self.out,
+            tree,
+            Tree::ROOT,
+            thres,
+            "",
+            true,
+            &self.config,
+        );
+        let _ = print_tree_summary(&mut self.out, tree, result.sims);
+    }
 }
 
 /// Run MCTS search from the given root position.
 ///
-/// Performs the specified number of simulations and returns the best move found.
-/// The best move is the most-visited child of the root.
+/// Performs the specified number of simulations and returns the result,
+/// whose best move is the most-visited child of the root.
 ///
 /// Includes early stopping: if the best move has a very high winrate early
 /// in the search, we stop early to save time.
-pub fn tree_search(root: &mut TreeNode, sims: usize) -> usize {
-    use crate::constants::{FASTPLAY5_THRES, FASTPLAY20_THRES};
+pub fn tree_search(tree: &mut Tree, sims: usize, config: &SearchConfig) -> SearchResult {
+    tree_search_observed(
+        tree,
+        sims,
+        config,
+        None,
+        &SearchLimits::default(),
+        &mut NullObserver,
+    )
+}
+
+/// Run MCTS search, reporting progress to `observer`.
+///
+/// Like `tree_search`, and besides:
+/// - Adds the ownership of every playout to `owner_map`, if given (it is not
+///   cleared first)
+/// - Stops at the deadline or cancellation in `limits`
+pub fn tree_search_observed(
+    tree: &mut Tree,
+    sims: usize,
+    config: &SearchConfig,
+    owner_map: Option<&mut [i32]>,
+    limits: &SearchLimits,
+    observer: &mut dyn SearchObserver,
+) -> SearchResult {
+    let start = Instant::now();
 
     // Initialize root if necessary
-    if root.children.is_empty() {
-        expand(root);
-    }
+    let root_pos = tree.pos().clone();
+    expand(tree, Tree::ROOT, &root_pos, config);
+
+    let (done, stop) = search_loop(tree, sims, config, owner_map, limits, observer);
+    let result = SearchResult::from_tree(tree, done, start.elapsed(), stop);
+    observer.on_finish(tree, &result);
+    result
+}
 
+/// The simulations of a search on a tree whose root is expanded. Returns
+/// the number of simulations run and why the search stopped.
+fn search_loop(
+    tree: &mut Tree,
+    sims: usize,
+    config: &SearchConfig,
+    mut owner_map: Option<&mut [i32]>,
+    limits: &SearchLimits,
+    observer: &mut dyn SearchObserver,
+) -> (usize, StopReason) {
+    use crate::constants::REPORT_PERIOD;
+
+    // The tree's generator, advanced here and stored back at the end
+    let mut rng = tree.rng_mut().clone();
     for i in 0..sims {
+        if i > 0 && i % REPORT_PERIOD == 0 {
+            observer.on_report(tree, i);
+        }
         let mut amaf_map = vec![0i8; BOARDSIZE];
 
-        // Descend to a leaf
-        let path = tree_descend(root, &mut amaf_map);
-
-        // Get position at the leaf and run a playout
-        let mut pos = get_leaf_position(root, &path);
-        let score = mcplayout(&mut pos, Some(&mut amaf_map));
+        // Descend to a leaf and run a playout from its position
+        let (path, mut pos) = tree_descend(tree, &mut amaf_map, config, &mut rng);
+        let score = match owner_map.as_deref_mut() {
+            Some(owner) => {
+                mcplayout_with_owner(&mut pos, Some(&mut amaf_map), owner, config, &mut rng)
+            }
+            None => mcplayout(&mut pos, Some(&mut amaf_map), config, &mut rng),
+        };
 
         // Update tree with the result
-        tree_update(root, &path, &amaf_map, score);
-
-        // Early stop test (same as michi-c)
-        // If best move has very high winrate, stop early
-        let best_wr = root
-            .children
-            .iter()
-            .filter(|c| c.v > 0)
-            .map(|c| c.winrate())
-            .fold(0.0_f64, f64::max);
-
-        if (i > sims / 20 && best_wr > FASTPLAY5_THRES)
-            || (i > sims / 5 && best_wr > FASTPLAY20_THRES)
-        {
-            break;
+        tree_update(tree, &path, &amaf_map, score);
+        observer.on_sim(tree, i + 1);
+
+        if let Some(reason) = early_stop(tree, i, sims, config, limits) {
+            *tree.rng_mut() = rng;
+            return (i + 1, reason);
         }
     }
-
-    // Return the best move (most visited child)
-    best_move(root)
+    *tree.rng_mut() = rng;
+    (sims, StopReason::Completed)
 }
 
-/// Find the best move (most visited child).
-fn best_move(tree: &TreeNode) -> usize {
-    tree.children
-        .iter()
-        .max_by_key(|c| c.v)
-        .map(|c| c.pos.last)
-        .unwrap_or(PASS_MOVE)
+/// Whether the best move's winrate is high enough after `i` of `sims`
+/// simulations to stop early (same as michi-c).
+fn fast_play(tree: &Tree, i: usize, sims: usize, config: &SearchConfig) -> bool {
+    let best_wr = best_root_winrate(tree);
+    (i > sims / 20 && best_wr > config.fastplay5_thres)
+        || (i > sims / 5 && best_wr > config.fastplay20_thres)
 }
 
 /// Print debug information about the root's children.
-pub fn dump_children(root: &TreeNode) {
-    for child in &root.children {
+pub fn dump_children(tree: &Tree) {
+    for child in tree.children(Tree::ROOT) {
         eprintln!(
             "move {} v={} w={} wr={:.3}",
-            str_coord(child.pos.last),
+            str_coord(child.mv()),
             child.v,
             child.w,
             child.winrate()
@@ -508,17 +726,21 @@ pub fn dump_children(root: &TreeNode) {
     }
 }
 
-/// RAVE urgency score for display purposes (same as internal rave_urgency).
-fn rave_urgency_display(node: &TreeNode) -> f64 {
-    rave_urgency(node)
-}
-
 /// Dump a subtree for display.
 ///
-/// Prints this node and all its children with v >= thres.
-/// If recurse is true, also prints grandchildren.
-pub fn dump_subtree(node: &TreeNode, thres: u32, indent: &str, recurse: bool) {
-    let move_str = str_coord(node.pos.last);
+/// Writes this node and all its children with v >= thres to `out`.
+/// If recurse is true, also writes grandchildren.
+pub fn dump_subtree(
+    out: &mut dyn Write,
+    tree: &Tree,
+    id: NodeId,
+    thres: u32,
+    indent: &str,
+    recurse: bool,
+    config: &SearchConfig,
+) -> io::Result<()> {
+    let node = tree.node(id);
+    let move_str = str_coord(node.mv());
     let winrate_str = if node.v > 0 {
         format!("{:.3}", node.winrate())
     } else {
@@ -530,7 +752,8 @@ pub fn dump_subtree(node: &TreeNode, thres: u32, indent: &str, recurse: bool) {
         " nan".to_string()
     };
 
-    eprintln!(
+    writeln!(
+        out,
         "{}+- {} {} ({:>6}/{:<6}, prior {:>3}/{:<3}, rave {:>6}/{:<6}={:>5}, urgency {:.3})",
         indent,
         move_str,
@@ -542,54 +765,73 @@ pub fn dump_subtree(node: &TreeNode, thres: u32, indent: &str, recurse: bool) {
         node.aw,
         node.av,
         rave_winrate_str,
-        rave_urgency_display(node)
-    );
+        rave_urgency(node, config.rave_equiv)
+    )?;
 
     if recurse {
         let new_indent = format!("{}   ", indent);
-        for child in &node.children {
-            if child.v >= thres {
-                dump_subtree(child, thres, &new_indent, false);
+        for child in tree.child_ids(id) {
+            if tree.node(child).v >= thres {
+                dump_subtree(out, tree, child, thres, &new_indent, false, config)?;
             }
         }
     }
+    Ok(())
 }
 
-/// Get the N best moves from a tree (by visit count).
-fn get_best_moves(tree: &TreeNode, n: usize) -> Vec<&TreeNode> {
+/// Get the N best children of a node (by visit count).
+pub fn get_best_moves(tree: &Tree, id: NodeId, n: usize) -> Vec<NodeId> {
     if n == 0 {
         return vec![];
     }
 
-    let mut children: Vec<&TreeNode> = tree.children.iter().collect();
+    let mut children: Vec<NodeId> = tree.child_ids(id).collect();
     if children.len() <= n {
         // If we need all or more elements than available, just sort everything
-        children.sort_by(|a, b| b.v.cmp(&a.v));
+        children.sort_by_key(|&c| std::cmp::Reverse(tree.node(c).v));
         return children;
     }
     // Partition so that the top n elements are at the front (in arbitrary order)
-    children.select_nth_unstable_by(n - 1, |a, b| b.v.cmp(&a.v));
+    children.select_nth_unstable_by(n - 1, |&a, &b| tree.node(b).v.cmp(&tree.node(a).v));
 
     // Take the top n elements and sort them
-    let mut best: Vec<&TreeNode> = children.into_iter().take(n).collect();
-    best.sort_by(|a, b| b.v.cmp(&a.v));
+    let mut best: Vec<NodeId> = children.into_iter().take(n).collect();
+    best.sort_by_key(|&c| std::cmp::Reverse(tree.node(c).v));
     best
 }
 
-/// Print a summary of the search progress.
+/// The principal variation from a node: its move, then the moves of the
+/// most visited children down the tree, at most `max_len` moves in all.
+pub fn principal_variation(tree: &Tree, id: NodeId, max_len: usize) -> Vec<Point> {
+    let mut pv = vec![tree.node(id).mv()];
+    let mut node = id;
+    while pv.len() < max_len {
+        match get_best_moves(tree, node, 1).first() {
+            Some(&best) if tree.node(best).v > 0 => {
+                pv.push(tree.node(best).mv());
+                node = best;
+            }
+            _ => break,
+        }
+    }
+    pv
+}
+
+/// Write a summary of the search progress to `out`.
 ///
 /// Shows current simulation count, best winrate, best sequence, and candidate moves.
-pub fn print_tree_summary(tree: &TreeNode, sims: usize) {
+pub fn print_tree_summary(out: &mut dyn Write, tree: &Tree, sims: usize) -> io::Result<()> {
     // Get 5 best candidate moves
-    let best_nodes = get_best_moves(tree, 5);
+    let best_nodes = get_best_moves(tree, Tree::ROOT, 5);
     if best_nodes.is_empty() {
-        return;
+        return Ok(());
     }
 
     // Format candidate moves with winrates
     let mut can = String::new();
-    for node in &best_nodes {
-        let move_str = str_coord(node.pos.last);
+    for &id in &best_nodes {
+        let node = tree.node(id);
+        let move_str = str_coord(node.mv());
         let wr_str = if node.v > 0 {
             format!("{:.3}", node.winrate())
         } else {
@@ -603,95 +845,306 @@ pub fn print_tree_summary(tree: &TreeNode, sims: usize) {
 
     // Get best sequence (up to 5 moves deep)
     let mut best_seq = String::new();
-    let mut node = tree;
+    let mut node = Tree::ROOT;
     for _ in 0..5 {
-        let best = get_best_moves(node, 1);
-        if best.is_empty() {
+        let Some(&best) = get_best_moves(tree, node, 1).first() else {
             break;
-        }
-        let best_child = best[0];
+        };
         if !best_seq.is_empty() {
             best_seq.push(' ');
         }
-        best_seq.push_str(&str_coord(best_child.pos.last));
-        // Find this child in the tree to continue
-        if let Some(child) = node
-            .children
-            .iter()
-            .find(|c| c.pos.last == best_child.pos.last)
-        {
-            node = child;
-        } else {
-            break;
-        }
+        best_seq.push_str(&str_coord(tree.node(best).mv()));
+        node = best;
     }
 
-    let best_wr = best_nodes[0].winrate();
-    eprintln!(
+    let best_wr = tree.node(best_nodes[0]).winrate();
+    writeln!(
+        out,
         "[{:>4}] winrate {:.3} | seq {}| can {}",
         sims, best_wr, best_seq, can
-    );
+    )
 }
 
 /// Run MCTS search with display and owner map tracking.
 ///
 /// This is the enhanced version that:
-/// - Tracks territory ownership for display
+/// - Tracks territory ownership for display (`owner_map` is cleared first)
 /// - Prints progress every REPORT_PERIOD simulations
 /// - Dumps subtree before returning
-pub fn tree_search_with_display(root: &mut TreeNode, sims: usize, owner_map: &mut [i32]) -> usize {
-    use crate::constants::{FASTPLAY5_THRES, FASTPLAY20_THRES, REPORT_PERIOD};
+/// - Stops at the deadline or cancellation in `limits`
+pub fn tree_search_with_display(
+    tree: &mut Tree,
+    sims: usize,
+    config: &SearchConfig,
+    owner_map: &mut [i32],
+    limits: &SearchLimits,
+) -> SearchResult {
+    owner_map.iter_mut().for_each(|x| *x = 0);
+    tree_search_observed(
+        tree,
+        sims,
+        config,
+        Some(owner_map),
+        limits,
+        &mut LogObserver::stderr(config),
+    )
+}
 
-    // Initialize root if necessary
-    if root.children.is_empty() {
-        expand(root);
+/// Why a search should stop after `i` of `sims` simulations, if it should.
+fn early_stop(
+    tree: &Tree,
+    i: usize,
+    sims: usize,
+    config: &SearchConfig,
+    limits: &SearchLimits,
+) -> Option<StopReason> {
+    let SearchLimits { deadline, cancel } = limits;
+    if cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+        Some(StopReason::Cancelled)
+    } else if fast_play(tree, i, sims, config) {
+        Some(StopReason::FastPlay)
+    } else if deadline.as_ref().is_some_and(|d| out_of_time(tree, d)) {
+        Some(StopReason::Deadline)
+    } else {
+        None
+    }
+}
+
+/// Whether a search should stop at `deadline`: always past the hard limit,
+/// and past the soft limit unless the best move is still unclear, i.e. the
+/// most visited root child does not lead the next one by `UNCLEAR_LEAD`.
+pub fn out_of_time(tree: &Tree, deadline: &Deadline) -> bool {
+    let now = Instant::now();
+    if now >= deadline.hard {
+        return true;
+    }
+    if now < deadline.soft {
+        return false;
+    }
+    match get_best_moves(tree, Tree::ROOT, 2).as_slice() {
+        &[best, second] => {
+            f64::from(tree.node(best).v) >= f64::from(tree.node(second).v) * UNCLEAR_LEAD
+        }
+        _ => true,
     }
+}
 
-    // Clear owner map
+/// Run MCTS search over `threads` threads with root parallelism.
+///
+/// Each thread searches its own copy of the tree for its share of the `sims`
+/// simulations, and of the room left under the node cap; the statistics the
+/// copies gathered are then summed back into `tree`, and the ownership
+/// into `owner_map`, which is cleared first.
+///
+/// With a single thread this is `tree_search_observed`. With several, the
+/// first thread's search runs on the calling thread and drives `on_sim` and
+/// `on_report` with its own tree and simulation count; the other threads
+/// search quietly. `on_finish` sees the merged tree and the total count.
+pub fn tree_search_parallel(
+    tree: &mut Tree,
+    sims: usize,
+    threads: usize,
+    config: &SearchConfig,
+    owner_map: &mut [i32],
+    limits: &SearchLimits,
+    observer: &mut dyn SearchObserver,
+) -> SearchResult {
     owner_map.iter_mut().for_each(|x| *x = 0);
+    if threads <= 1 {
+        return tree_search_observed(tree, sims, config, Some(owner_map), limits, observer);
+    }
 
-    let mut actual_sims = 0;
-    for i in 0..sims {
-        actual_sims = i + 1;
-        let mut amaf_map = vec![0i8; BOARDSIZE];
+    let start = Instant::now();
+
+    // Expand the root first so that every copy shares its children
+    let root_pos = tree.pos().clone();
+    expand(tree, Tree::ROOT, &root_pos, config);
+
+    let base = tree.clone();
+    let room = tree.max_nodes().saturating_sub(tree.node_count()) / threads;
+    let mut copies: Vec<Tree> = (0..threads)
+        .map(|_| {
+            let mut copy = base.clone();
+            copy.set_max_nodes(base.node_count() + room);
+            // Each thread draws from its own generator, forked from
+            // the tree's so that a seeded search stays reproducible
+            *copy.rng_mut() = tree.rng_mut().fork();
+            copy
+        })
+        .collect();
+    let share = |t: usize| sims / threads + usize::from(t < sims % threads);
+    let search = |mut copy: Tree, share: usize, observer: &mut dyn SearchObserver| {
+        let mut owner = vec![0i32; BOARDSIZE];
+        let (done, stop) =
+            search_loop(&mut copy, share, config, Some(&mut owner), limits, observer);
+        (copy, owner, done, stop)
+    };
+    let first = copies.remove(0);
+    let results: Vec<(Tree, Vec<i32>, usize, StopReason)> = std::thread::scope(|s| {
+        let workers: Vec<_> = copies
+            .into_iter()
+            .enumerate()
+            .map(|(t, copy)| s.spawn(move || search(copy, share(t + 1), &mut NullObserver)))
+            .collect();
+        // The observer is not `Send`, so it follows the search on this thread
+        let mut results = vec![search(first, share(0), observer)];
+        results.extend(
+            workers
+                .into_iter()
+                .map(|w| w.join().expect("search thread panicked")),
+        );
+        results
+    });
 
-        // Print progress periodically
-        if i > 0 && i % REPORT_PERIOD == 0 {
-            print_tree_summary(root, i);
+    let mut actual_sims = 0;
+    let mut stop = StopReason::Completed;
+    for (copy, owner, done, worker_stop) in &results {
+        merge_stats(
+            tree,
+            Tree::ROOT,
+            copy,
+            Tree::ROOT,
+            Some((&base, Tree::ROOT)),
+        );
+        for (total, o) in owner_map.iter_mut().zip(owner) {
+            *total += o;
         }
+        actual_sims += done;
+        stop = stop.max(*worker_stop);
+    }
 
-        // Descend to a leaf
-        let path = tree_descend(root, &mut amaf_map);
+    let result = SearchResult::from_tree(tree, actual_sims, start.elapsed(), stop);
+    observer.on_finish(tree, &result);
+    result
+}
 
-        // Get position at the leaf and run a playout
-        let mut pos = get_leaf_position(root, &path);
-        let score = mcplayout_with_owner(&mut pos, Some(&mut amaf_map), owner_map);
+/// Run a quiet MCTS search that adds the ownership of every playout to
+/// `owner_map` without clearing it first, for continuous analysis.
+///
+/// Stops early like `tree_search`.
+pub fn tree_search_with_owner(
+    tree: &mut Tree,
+    sims: usize,
+    config: &SearchConfig,
+    owner_map: &mut [i32],
+) -> SearchResult {
+    tree_search_observed(
+        tree,
+        sims,
+        config,
+        Some(owner_map),
+        &SearchLimits::default(),
+        &mut NullObserver,
+    )
+}
 
-        // Update tree with the result
-        tree_update(root, &path, &amaf_map, score);
-
-        // Early stop test (same as michi-c)
-        let best_wr = root
-            .children
-            .iter()
-            .filter(|c| c.v > 0)
-            .map(|c| c.winrate())
-            .fold(0.0_f64, f64::max);
-
-        if (i > sims / 20 && best_wr > FASTPLAY5_THRES)
-            || (i > sims / 5 && best_wr > FASTPLAY20_THRES)
-        {
-            break;
-        }
+/// Add the statistics node `src_id` of `src` gathered since it was copied
+/// from `base` into node `id` of `dst`, recursively.
+///
+/// Expansion is deterministic, so nodes expanded in both trees have their
+/// children in the same order. Subtrees that only `src` expanded are copied.
+fn merge_stats(
+    dst: &mut Tree,
+    id: NodeId,
+    src: &Tree,
+    src_id: NodeId,
+    base: Option<(&Tree, NodeId)>,
+) {
+    let s = *src.node(src_id);
+    let (v, w, av, aw) = base.map_or((0, 0, 0, 0), |(b, b_id)| {
+        let b = b.node(b_id);
+        (b.v, b.w, b.av, b.aw)
+    });
+    let d = dst.node_mut(id);
+    d.v += s.v - v;
+    d.w += s.w - w;
+    d.av += s.av - av;
+    d.aw += s.aw - aw;
+
+    if !s.is_expanded() {
+        return;
     }
+    if !dst.node(id).is_expanded() {
+        dst.copy_subtree(id, src, src_id);
+        return;
+    }
+    let base_children = base
+        .filter(|(b, b_id)| b.node(*b_id).is_expanded())
+        .map(|(b, b_id)| (b, b.child_ids(b_id).start));
+    for (i, (child, src_child)) in dst.child_ids(id).zip(src.child_ids(src_id)).enumerate() {
+        let base_child = base_children.map(|(b, first)| (b, first + i as NodeId));
+        merge_stats(dst, child, src, src_child, base_child);
+    }
+}
+
+/// Estimate who owns each point of `pos` by running `sims` playouts from it.
+///
+/// `owner_map` is cleared first; afterwards each entry is the number of
+/// playouts that ended with the point owned by Black minus those owned by White.
+pub fn estimate_ownership(
+    pos: &Position,
+    sims: usize,
+    config: &SearchConfig,
+    owner_map: &mut [i32],
+    rng: &mut Rng,
+) {
+    owner_map.iter_mut().for_each(|x| *x = 0);
+    for _ in 0..sims {
+        let mut playout_pos = pos.clone();
+        mcplayout_with_owner(&mut playout_pos, None, owner_map, config, rng);
+    }
+}
 
-    // Dump subtree before returning (threshold = N_SIMS/50)
-    let thres = (sims / 50) as u32;
-    dump_subtree(root, thres, "", true);
-    print_tree_summary(root, actual_sims);
+/// Life-and-death status of a group at the end of the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupStatus {
+    Alive,
+    Dead,
+    Seki,
+}
+
+/// Classify every group on the board from an owner map built with `sims`
+/// playouts (see [`estimate_ownership`]).
+///
+/// A group's score is the average ownership of its stones from its own
+/// point of view, in [-1, 1]; `STATUS_THRES` separates alive, seki and dead.
+/// Returns each group's stones with its status.
+pub fn group_statuses(
+    pos: &Position,
+    owner_map: &[i32],
+    sims: usize,
+) -> Vec<(Vec<Point>, GroupStatus)> {
+    let mut visited = [false; BOARDSIZE];
+    let mut groups = Vec::new();
+
+    for pt in BOARD_IMIN..pos.imax() {
+        let c = pos.color[pt];
+        if (c != STONE_BLACK && c != STONE_WHITE) || visited[pt] {
+            continue;
+        }
+        let (stones, _) = compute_block(pos, pt, BOARDSIZE);
+        stones.iter().for_each(|&s| visited[s] = true);
+
+        // 'X' is Black when Black is to play; owner_map is positive for Black
+        let sign = if (c == STONE_BLACK) == pos.is_black_to_play() {
+            1.0
+        } else {
+            -1.0
+        };
+        let owned: f64 = stones.iter().map(|&s| owner_map[s] as f64).sum();
+        let own = sign * owned / (stones.len() * sims.max(1)) as f64;
+
+        let status = if own > STATUS_THRES {
+            GroupStatus::Alive
+        } else if own < -STATUS_THRES {
+            GroupStatus::Dead
+        } else {
+            GroupStatus::Seki
+        };
+        groups.push((stones, status));
+    }
 
-    // Return the best move (most visited child)
-    best_move(root)
+    groups
 }
 
 /// Perform a Monte Carlo playout and update owner map.
@@ -701,15 +1154,18 @@ fn mcplayout_with_owner(
     pos: &mut Position,
     amaf_map: Option<&mut [i8]>,
     owner_map: &mut [i32],
+    config: &SearchConfig,
+    rng: &mut Rng,
 ) -> f64 {
-    let score = mcplayout(pos, amaf_map);
+    let score = mcplayout(pos, amaf_map, config, rng);
 
     // Update owner map based on final position
     // Positive for Black stones/territory, negative for White
     // n = 1 if Black to play, -1 if White to play (same as C version)
     let n: i32 = if pos.is_black_to_play() { 1 } else { -1 };
 
-    for pt in BOARD_IMIN..BOARD_IMAX {
+    let imax = pos.imax();
+    for (pt, owner) in owner_map.iter_mut().enumerate().take(imax).skip(BOARD_IMIN) {
         // For empty points, check if surrounded by one color (territory)
         let c = if pos.color[pt] == EMPTY {
             is_eyeish(pos, pt)
@@ -719,8 +1175,8 @@ fn mcplayout_with_owner(
 
         // 'X' = current player's stones/territory, 'x' = opponent's
         match c {
-            b'X' => owner_map[pt] += n,
-            b'x' => owner_map[pt] -= n,
+            b'X' => *owner += n,
+            b'x' => *owner -= n,
             _ => {}
         }
     }
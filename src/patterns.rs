@@ -14,7 +14,7 @@
 //! These provide probability estimates for how likely a move is to be good.
 //! Loaded from `patterns.prob` and `patterns.spat` files.
 
-use crate::constants::N;
+use crate::constants::{MAX_N, W};
 use crate::position::{Point, Position};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -78,8 +78,12 @@ const HASHTABLE_LENGTH: usize = 1 << KSIZE;
 /// Mask for extracting key from hash.
 const KMASK: usize = HASHTABLE_LENGTH - 1;
 
-/// Large board size with 7-layer border for pattern computation.
-const LARGE_BOARDSIZE: usize = (N + 14) * (N + 7);
+/// Row width of the large board with 7-layer border for pattern computation.
+///
+/// The width is fixed for the largest supported board so that the gridcular
+/// offsets are the same for every board size; smaller boards simply leave
+/// the extra columns as border.
+const LARGE_W: usize = MAX_N + 7;
 
 /// Maximum pattern neighborhood size (141 points).
 const MAX_PATTERN_DIST: usize = 141;
@@ -229,7 +233,7 @@ fn pat_wildexp(src: &[u8; 9], i: usize, pat3set: &mut [u8; 8192]) {
     match src[i] {
         b'?' => {
             // Any of X, O, ., #
-            for &c in &[b'X', b'O', b'.', b'#'] {
+            for &c in b"XO.#" {
                 let mut new_src = *src;
                 new_src[i] = c;
                 pat_wildexp(&new_src, i + 1, pat3set);
@@ -237,7 +241,7 @@ fn pat_wildexp(src: &[u8; 9], i: usize, pat3set: &mut [u8; 8192]) {
         }
         b'x' => {
             // Not X (O, ., or #)
-            for &c in &[b'O', b'.', b'#'] {
+            for &c in b"O.#" {
                 let mut new_src = *src;
                 new_src[i] = c;
                 pat_wildexp(&new_src, i + 1, pat3set);
@@ -245,7 +249,7 @@ fn pat_wildexp(src: &[u8; 9], i: usize, pat3set: &mut [u8; 8192]) {
         }
         b'o' => {
             // Not O (X, ., or #)
-            for &c in &[b'X', b'.', b'#'] {
+            for &c in b"X.#" {
                 let mut new_src = *src;
                 new_src[i] = c;
                 pat_wildexp(&new_src, i + 1, pat3set);
@@ -356,6 +360,12 @@ fn rot90(src: &mut [u8; 9]) {
 // Large Pattern Implementation
 // =============================================================================
 
+impl Default for LargePatternDb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl LargePatternDb {
     /// Create a new empty pattern database.
     pub fn new() -> Self {
@@ -394,10 +404,9 @@ impl LargePatternDb {
 
     /// Initialize gridcular 1D offsets.
     fn init_gridcular(&mut self) {
-        let large_w = (N + 7) as isize;
-        for i in 0..MAX_PATTERN_DIST {
-            let (x, y) = PAT_GRIDCULAR_SEQ[i];
-            self.gridcular_seq1d[i] = (x as isize) - (y as isize) * large_w;
+        let large_w = LARGE_W as isize;
+        for (d, &(x, y)) in self.gridcular_seq1d.iter_mut().zip(&PAT_GRIDCULAR_SEQ) {
+            *d = (x as isize) - (y as isize) * large_w;
         }
     }
 
@@ -553,7 +562,7 @@ impl LargePatternDb {
 
     /// Compute the 8 permutations for pattern rotations/reflections.
     fn compute_permutations(&self) -> Vec<Vec<usize>> {
-        let large_w = (N + 7) as isize;
+        let large_w = LARGE_W as isize;
         let base_seq1d: Vec<isize> = PAT_GRIDCULAR_SEQ
             .iter()
             .map(|(x, y)| (*x as isize) - (*y as isize) * large_w)
@@ -621,15 +630,14 @@ impl LargePatternDb {
 
         // Build large board representation for this point
         let large_board = self.build_large_board(pos);
-        let large_pt = self.point_to_large_coord(pt);
+        let large_pt = self.point_to_large_coord(pos, pt);
 
         let mut prob = -1.0;
         let mut matched_len = 0;
         let mut non_matched_len = 0;
         let mut k: ZobristHash = 0;
 
-        for s in 1..13 {
-            let len = PAT_GRIDCULAR_SIZE[s];
+        for (s, &len) in PAT_GRIDCULAR_SIZE.iter().enumerate().skip(1) {
             k = self.update_zobrist_hash(&large_board, large_pt, s, k);
             let i = self.find_pat(k);
             if self.patterns[i].key == k {
@@ -654,7 +662,7 @@ impl LargePatternDb {
 
         // Build large board representation for this point
         let large_board = self.build_large_board(pos);
-        let large_pt = self.point_to_large_coord(pt);
+        let large_pt = self.point_to_large_coord(pos, pt);
 
         let mut ids = Vec::new();
         let mut k: ZobristHash = 0;
@@ -671,15 +679,17 @@ impl LargePatternDb {
     }
 
     /// Build a large board representation with 7-layer border.
+    ///
+    /// The large board is laid out top row first, like the C code.
     fn build_large_board(&self, pos: &Position) -> Vec<u8> {
-        let mut large_board = vec![b'#'; LARGE_BOARDSIZE];
-        let large_w = N + 7;
+        let n = pos.size;
+        let mut large_board = vec![b'#'; (n + 14) * LARGE_W];
 
         // Copy position to large board
-        for y in 0..N {
-            for x in 0..N {
-                let pt = (y + 1) * (N + 1) + x + 1;
-                let lpt = (y + 7) * large_w + x + 7;
+        for y in 0..n {
+            for x in 0..n {
+                let pt = (n - y) * W + x + 1;
+                let lpt = (y + 7) * LARGE_W + x + 7;
                 large_board[lpt] = pos.color[pt];
             }
         }
@@ -688,10 +698,10 @@ impl LargePatternDb {
     }
 
     /// Convert a board point to large board coordinate.
-    fn point_to_large_coord(&self, pt: Point) -> usize {
-        let y = pt / (N + 1) - 1;
-        let x = pt % (N + 1) - 1;
-        (y + 7) * (N + 7) + x + 7
+    fn point_to_large_coord(&self, pos: &Position, pt: Point) -> usize {
+        let y = pos.size - pt / W;
+        let x = pt % W - 1;
+        (y + 7) * LARGE_W + x + 7
     }
 
     /// Update Zobrist hash for points in a neighborhood size.
@@ -8,26 +8,15 @@
 //! - 3x3 pattern matching
 //! - Self-atari rejection
 
-use crate::constants::{
-    BOARD_IMAX, BOARD_IMIN, EMPTY, MAX_GAME_LEN, N, PROB_HEURISTIC_CAPTURE, PROB_HEURISTIC_PAT3,
-    PROB_RSAREJECT, PROB_SSAREJECT, STONE_BLACK, W,
-};
+use fastrand::Rng;
+
+use crate::config::SearchConfig;
+use crate::constants::{BOARD_IMIN, BOARDSIZE, EMPTY, MAX_N, STONE_BLACK, STONE_WHITE};
 use crate::patterns::pat3_match;
 use crate::position::{
-    Point, Position, all_neighbors, fix_atari, is_eye, is_eyeish, pass_move, play_move,
+    Point, Position, all_neighbors, fix_atari, is_eye, is_eyeish, neighbors, pass_move, play_move,
 };
-
-/// Seed the random number generator.
-#[allow(dead_code)]
-pub fn seed_rng(seed: u64) {
-    fastrand::seed(seed);
-}
-
-/// Generate a random float in [0, 1).
-#[inline]
-fn random_float() -> f64 {
-    fastrand::f64()
-}
+use crate::rules::Scoring;
 
 /// Perform a Monte Carlo playout from the given position.
 ///
@@ -38,18 +27,26 @@ fn random_float() -> f64 {
 ///
 /// If `amaf_map` is provided, updates it with who played at each position first
 /// (1 for Black, -1 for White). This is used for RAVE/AMAF heuristic in MCTS.
-pub fn mcplayout(pos: &mut Position, mut amaf_map: Option<&mut [i8]>) -> f64 {
+///
+/// The heuristics are used with the probabilities set in `config`, and all
+/// random choices are drawn from `rng`, so a seeded `rng` replays the playout.
+pub fn mcplayout(
+    pos: &mut Position,
+    mut amaf_map: Option<&mut [i8]>,
+    config: &SearchConfig,
+    rng: &mut Rng,
+) -> f64 {
     let start_n = pos.n;
     let mut passes = 0;
 
-    while passes < 2 && pos.n < MAX_GAME_LEN {
-        if let Some(pt) = choose_playout_move(pos) {
+    while passes < 2 && pos.n < pos.max_game_len() {
+        if let Some(pt) = choose_playout_move(pos, config, rng) {
             // Update AMAF map before playing the move
-            if let Some(ref mut amaf) = amaf_map {
-                if amaf[pt] == 0 {
-                    // Mark with 1 for black, -1 for white
-                    amaf[pt] = if pos.is_black_to_play() { 1 } else { -1 };
-                }
+            if let Some(ref mut amaf) = amaf_map
+                && amaf[pt] == 0
+            {
+                // Mark with 1 for black, -1 for white
+                amaf[pt] = if pos.is_black_to_play() { 1 } else { -1 };
             }
             play_move(pos, pt).expect("Chosen move should be legal");
             passes = 0;
@@ -72,7 +69,7 @@ pub fn mcplayout(pos: &mut Position, mut amaf_map: Option<&mut [i8]>) -> f64 {
 /// 3. Random legal move
 ///
 /// Also rejects self-atari moves with high probability.
-fn choose_playout_move(pos: &Position) -> Option<Point> {
+fn choose_playout_move(pos: &Position, config: &SearchConfig, rng: &mut Rng) -> Option<Point> {
     // Get the neighborhood of the last two moves for focused heuristics
     // Optimization: Use stack array instead of Vec allocation to avoid heap overhead in hot path
     let mut neighbors = [0; 20];
@@ -83,83 +80,63 @@ fn choose_playout_move(pos: &Position) -> Option<Point> {
         neighbors[count] = pos.last;
         count += 1;
         for n in all_neighbors(pos.last) {
-            if pos.color[n] != b' ' {
-                // Check for duplicates (linear scan is fast for small array)
-                let mut found = false;
-                for i in 0..count {
-                    if neighbors[i] == n {
-                        found = true;
-                        break;
-                    }
-                }
-                if !found {
-                    neighbors[count] = n;
-                    count += 1;
-                }
+            // Check for duplicates (linear scan is fast for small array)
+            if pos.color[n] != b' ' && !neighbors[..count].contains(&n) {
+                neighbors[count] = n;
+                count += 1;
             }
         }
     }
 
     // Add last2 move and its neighbors
     if pos.last2 != 0 {
-        let mut found = false;
-        for i in 0..count {
-            if neighbors[i] == pos.last2 {
-                found = true;
-                break;
-            }
-        }
-        if !found {
+        if !neighbors[..count].contains(&pos.last2) {
             neighbors[count] = pos.last2;
             count += 1;
         }
 
         for n in all_neighbors(pos.last2) {
-            if pos.color[n] != b' ' {
-                let mut found = false;
-                for i in 0..count {
-                    if neighbors[i] == n {
-                        found = true;
-                        break;
-                    }
-                }
-                if !found {
-                    neighbors[count] = n;
-                    count += 1;
-                }
+            if pos.color[n] != b' ' && !neighbors[..count].contains(&n) {
+                neighbors[count] = n;
+                count += 1;
             }
         }
     }
 
     // Shuffle for randomization
     let neighbors_slice = &mut neighbors[0..count];
-    fastrand::shuffle(neighbors_slice);
+    rng.shuffle(neighbors_slice);
 
-    // 1. Try capture heuristics (with probability PROB_HEURISTIC_CAPTURE)
-    if random_float() < PROB_HEURISTIC_CAPTURE {
-        if let Some(mv) = try_capture_moves(pos, neighbors_slice) {
-            return Some(mv);
-        }
+    // 1. Try capture heuristics (with probability prob_heuristic_capture)
+    if rng.f64() < config.prob_heuristic_capture
+        && let Some(mv) = try_capture_moves(pos, neighbors_slice, config, rng)
+    {
+        return Some(mv);
     }
 
-    // 2. Try 3x3 pattern moves (with probability PROB_HEURISTIC_PAT3)
-    if random_float() < PROB_HEURISTIC_PAT3 {
-        if let Some(mv) = try_pattern_moves(pos, neighbors_slice) {
-            return Some(mv);
-        }
+    // 2. Try 3x3 pattern moves (with probability prob_heuristic_pat3)
+    if rng.f64() < config.prob_heuristic_pat3
+        && let Some(mv) = try_pattern_moves(pos, neighbors_slice, config, rng)
+    {
+        return Some(mv);
     }
 
     // 3. Fall back to random move
-    choose_random_move(pos)
+    choose_random_move(pos, config, rng)
 }
 
 /// Try to find a capture move among the neighbor points.
-fn try_capture_moves(pos: &Position, neighbors: &[Point]) -> Option<Point> {
+fn try_capture_moves(
+    pos: &Position,
+    neighbors: &[Point],
+    config: &SearchConfig,
+    rng: &mut Rng,
+) -> Option<Point> {
     for &pt in neighbors {
         if pos.color[pt] == STONE_BLACK || pos.color[pt] == b'x' {
             let moves = fix_atari(pos, pt, false);
             for mv in moves {
-                if try_move_with_self_atari_check(pos, mv, false) {
+                if try_move_with_self_atari_check(pos, mv, false, config, rng) {
                     return Some(mv);
                 }
             }
@@ -169,35 +146,49 @@ fn try_capture_moves(pos: &Position, neighbors: &[Point]) -> Option<Point> {
 }
 
 /// Try to find a 3x3 pattern move among the neighbor points.
-fn try_pattern_moves(pos: &Position, neighbors: &[Point]) -> Option<Point> {
-    for &pt in neighbors {
-        if pos.color[pt] == EMPTY && pat3_match(pos, pt) {
-            if try_move_with_self_atari_check(pos, pt, false) {
-                return Some(pt);
-            }
-        }
-    }
-    None
+fn try_pattern_moves(
+    pos: &Position,
+    neighbors: &[Point],
+    config: &SearchConfig,
+    rng: &mut Rng,
+) -> Option<Point> {
+    neighbors
+        .iter()
+        .find(|&&pt| {
+            pos.color[pt] == EMPTY
+                && pat3_match(pos, pt)
+                && try_move_with_self_atari_check(pos, pt, false, config, rng)
+        })
+        .copied()
 }
 
 /// Check if a move is legal and not a self-atari (with probability-based rejection).
 ///
-/// `is_random`: if true, uses lower rejection probability (PROB_RSAREJECT = 0.5)
-///              if false, uses higher rejection probability (PROB_SSAREJECT = 0.9)
-fn try_move_with_self_atari_check(pos: &Position, pt: Point, is_random: bool) -> bool {
+/// `is_random`: if true, uses lower rejection probability (prob_rsareject, 0.5 by default)
+///              if false, uses higher rejection probability (prob_ssareject, 0.9 by default)
+fn try_move_with_self_atari_check(
+    pos: &Position,
+    pt: Point,
+    is_random: bool,
+    config: &SearchConfig,
+    rng: &mut Rng,
+) -> bool {
     let mut test_pos = pos.clone();
     if play_move(&mut test_pos, pt).is_err() {
         return false; // Illegal move
     }
+    if test_pos.color[pt] == EMPTY {
+        return false; // Suicide (when the rules allow it) is never useful
+    }
 
     // Check for self-atari and reject with probability based on move type
     // Random moves use lower rejection rate to allow more nakade/tactical moves
     let reject_prob = if is_random {
-        PROB_RSAREJECT
+        config.prob_rsareject
     } else {
-        PROB_SSAREJECT
+        config.prob_ssareject
     };
-    if random_float() < reject_prob {
+    if rng.f64() < reject_prob {
         let moves = fix_atari(&test_pos, pt, true);
         if !moves.is_empty() {
             // This move puts us in atari - reject it
@@ -212,17 +203,18 @@ fn try_move_with_self_atari_check(pos: &Position, pt: Point, is_random: bool) ->
 ///
 /// Uses random starting index for fairness, similar to the C implementation.
 /// Optimized with stack allocation to avoid heap overhead.
-fn choose_random_move(pos: &Position) -> Option<usize> {
+fn choose_random_move(pos: &Position, config: &SearchConfig, rng: &mut Rng) -> Option<usize> {
     // Collect candidate moves (empty points that aren't true eyes)
     // Use stack array instead of Vec to avoid heap allocation in hot path
-    let mut candidates = [0; N * N];
+    let mut candidates = [0; MAX_N * MAX_N];
     let mut count = 0;
 
     // Start from a random index for better randomization
-    let start = BOARD_IMIN + fastrand::usize(0..N * W);
+    let imax = pos.imax();
+    let start = rng.usize(BOARD_IMIN..imax);
 
     // Scan from start to end
-    for pt in start..BOARD_IMAX {
+    for pt in start..imax {
         if pos.color[pt] == EMPTY && is_eye(pos, pt) != b'X' {
             candidates[count] = pt;
             count += 1;
@@ -244,13 +236,13 @@ fn choose_random_move(pos: &Position) -> Option<usize> {
     // (some candidates might be suicide moves or self-atari)
     for i in 0..count {
         // Pick a random remaining candidate
-        let j = i + fastrand::usize(0..count - i);
+        let j = i + rng.usize(0..count - i);
         candidates.swap(i, j);
 
         let pt = candidates[i];
 
         // Use is_random=true for lower self-atari rejection rate
-        if try_move_with_self_atari_check(pos, pt, true) {
+        if try_move_with_self_atari_check(pos, pt, true, config, rng) {
             return Some(pt);
         }
     }
@@ -258,33 +250,136 @@ fn choose_random_move(pos: &Position) -> Option<usize> {
     None
 }
 
-/// Compute the score for the current player.
+/// Compute the score for the current player under the position's rules.
 ///
-/// Uses area scoring (Chinese rules):
-/// - Stones on the board count as territory
-/// - Eyeish empty points belong to the surrounding color
-/// - Komi is applied (negative for Black, positive for White)
+/// - Area scoring (Chinese rules): stones count as territory, and eyeish
+///   empty points belong to the surrounding color
+/// - Territory scoring (Japanese rules): eyeish empty points plus prisoners
+/// - Tromp-Taylor scoring: stones plus empty regions reaching only one color
+///
+/// Komi, including any handicap compensation, counts for White.
 ///
 /// Returns a positive score if the current player ('X') is winning.
-fn score(pos: &Position) -> f64 {
+pub fn score(pos: &Position) -> f64 {
     // Start with komi adjustment
+    let komi = pos.rules.effective_komi(pos) as f64;
     let mut s = if pos.is_black_to_play() {
-        -pos.komi as f64 // Black to play, komi counts against Black
+        -komi // Black to play, komi counts against Black
     } else {
-        pos.komi as f64 // White to play, komi counts for White
+        komi // White to play, komi counts for White
     };
 
-    for pt in BOARD_IMIN..BOARD_IMAX {
-        let c = pos.color[pt];
-        // For empty points, check if they're controlled by one side
-        let effective = if c == EMPTY { is_eyeish(pos, pt) } else { c };
+    match pos.rules.scoring {
+        Scoring::Area => {
+            for pt in BOARD_IMIN..pos.imax() {
+                let c = pos.color[pt];
+                // For empty points, check if they're controlled by one side
+                let effective = if c == EMPTY { is_eyeish(pos, pt) } else { c };
+                s += point_value(effective);
+            }
+        }
+        Scoring::Territory => {
+            for pt in BOARD_IMIN..pos.imax() {
+                if pos.color[pt] == EMPTY {
+                    s += point_value(is_eyeish(pos, pt));
+                }
+            }
+            s += pos.cap_x as f64 - pos.cap as f64;
+        }
+        Scoring::TrompTaylor => {
+            let mut visited = [false; BOARDSIZE];
+            for pt in BOARD_IMIN..pos.imax() {
+                match pos.color[pt] {
+                    EMPTY if !visited[pt] => {
+                        let (size, owner) = empty_region(pos, pt, &mut visited);
+                        s += size as f64 * point_value(owner);
+                    }
+                    c => s += point_value(c),
+                }
+            }
+        }
+    }
+
+    s
+}
 
-        match effective {
-            b'X' => s += 1.0,
-            b'x' => s -= 1.0,
-            _ => {} // Empty or neutral
+/// Score a finished game for the current player.
+///
+/// The `dead` stones are taken off the board as prisoners, then empty regions
+/// that reach only one color count as territory (regions touching both, e.g.
+/// in seki, are neutral). Stones are added under area and Tromp-Taylor scoring,
+/// prisoners under territory scoring. Komi is applied as in [`score`].
+pub fn final_score(pos: &Position, dead: &[Point]) -> f64 {
+    let mut pos = pos.clone();
+    for &pt in dead {
+        match pos.color[pt] {
+            STONE_BLACK => pos.cap += 1,   // the opponent takes it prisoner
+            STONE_WHITE => pos.cap_x += 1, // the current player takes it prisoner
+            _ => continue,
+        }
+        pos.color[pt] = EMPTY;
+    }
+
+    let komi = pos.rules.effective_komi(&pos) as f64;
+    let mut s = if pos.is_black_to_play() { -komi } else { komi };
+
+    let mut visited = [false; BOARDSIZE];
+    for pt in BOARD_IMIN..pos.imax() {
+        match pos.color[pt] {
+            EMPTY if !visited[pt] => {
+                let (size, owner) = empty_region(&pos, pt, &mut visited);
+                s += size as f64 * point_value(owner);
+            }
+            c if pos.rules.scoring != Scoring::Territory => s += point_value(c),
+            _ => {}
         }
     }
+    if pos.rules.scoring == Scoring::Territory {
+        s += pos.cap_x as f64 - pos.cap as f64;
+    }
 
     s
 }
+
+/// Value of a point owned by `c`: +1 for 'X', -1 for 'x', 0 otherwise.
+#[inline]
+fn point_value(c: u8) -> f64 {
+    match c {
+        STONE_BLACK => 1.0,
+        STONE_WHITE => -1.0,
+        _ => 0.0, // Empty or neutral
+    }
+}
+
+/// Flood-fill the empty region containing `start`.
+///
+/// Returns its size and the color it reaches, or `EMPTY` if it reaches both
+/// colors (or none).
+fn empty_region(pos: &Position, start: Point, visited: &mut [bool; BOARDSIZE]) -> (usize, u8) {
+    let mut stack = vec![start];
+    visited[start] = true;
+    let mut size = 0;
+    let (mut reaches_x, mut reaches_o) = (false, false);
+
+    while let Some(pt) = stack.pop() {
+        size += 1;
+        for n in neighbors(pt) {
+            match pos.color[n] {
+                EMPTY if !visited[n] => {
+                    visited[n] = true;
+                    stack.push(n);
+                }
+                STONE_BLACK => reaches_x = true,
+                STONE_WHITE => reaches_o = true,
+                _ => {}
+            }
+        }
+    }
+
+    let owner = match (reaches_x, reaches_o) {
+        (true, false) => STONE_BLACK,
+        (false, true) => STONE_WHITE,
+        _ => EMPTY,
+    };
+    (size, owner)
+}
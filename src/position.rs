@@ -3,7 +3,7 @@
 //! This module provides the core game logic for Go, including:
 //! - Board state representation using a 1D array with padding
 //! - Stone placement and capture detection
-//! - Ko rule enforcement
+//! - Ko rule enforcement, plus Zobrist hashing for superko
 //! - Eye detection for playout optimization
 //!
 //! The board uses a color-swapping scheme where the current player's stones
@@ -11,6 +11,9 @@
 //! move generation by always checking from the perspective of `'X'`.
 
 use crate::constants::*;
+use crate::rules::Rules;
+#[cfg(doc)]
+use crate::rules::Superko;
 
 /// A point on the board, represented as an index into the 1D board array.
 pub type Point = usize;
@@ -24,6 +27,8 @@ pub enum MoveError {
     Ko,
     /// Move would be suicide (no liberties after capture resolution)
     Suicide,
+    /// Move repeats an earlier position of the game (see [`Superko`])
+    Superko,
 }
 
 impl std::fmt::Display for MoveError {
@@ -32,6 +37,7 @@ impl std::fmt::Display for MoveError {
             MoveError::Occupied => "point not EMPTY",
             MoveError::Ko => "retakes ko",
             MoveError::Suicide => "suicide",
+            MoveError::Superko => "repeats an earlier position (superko)",
         };
         write!(f, "Error Illegal move: {}", msg)
     }
@@ -43,8 +49,13 @@ impl std::error::Error for MoveError {}
 ///
 /// The board is represented as a 1D array with padding around the edges.
 /// Colors are swapped after each move so that the current player is always `'X'`.
+///
+/// The arrays are sized for the largest supported board; `size` determines
+/// which points are playable and everything else is marked out of bounds.
 #[derive(Clone)]
 pub struct Position {
+    /// Board size (NxN)
+    pub size: usize,
     /// Board state: 'X' = current player, 'x' = opponent, '.' = empty, ' ' = out of bounds
     pub color: [u8; BOARDSIZE],
     /// Encoded colors of 4 orthogonal neighbors (N, E, S, W) for pattern matching.
@@ -66,17 +77,44 @@ pub struct Position {
     pub last2: Point,
     /// Third-to-last move
     pub last3: Point,
-    /// Captures by current player ('X')
+    /// Captures by the opponent ('x', the player who just moved)
     pub cap: u32,
-    /// Captures by opponent ('x')
+    /// Captures by the current player ('X')
     pub cap_x: u32,
     /// Komi (compensation points for White)
     pub komi: f32,
+    /// Number of handicap stones Black received (for handicap komi)
+    pub handicap: usize,
+    /// Rule set of the game (scoring, suicide, superko)
+    pub rules: Rules,
+    /// Zobrist hash of the stones on the board, by absolute color.
+    /// Updated incrementally by `put_stone`/`remove_stone`.
+    pub hash: u64,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Position {
+    /// Create an empty position on a board of the default size.
     pub fn new() -> Self {
+        Self::with_size(DEFAULT_N)
+    }
+
+    /// Create an empty position on an `size`x`size` board.
+    ///
+    /// # Panics
+    /// Panics if `size` is outside `MIN_N..=MAX_N`.
+    pub fn with_size(size: usize) -> Self {
+        assert!(
+            (MIN_N..=MAX_N).contains(&size),
+            "unsupported board size {size}"
+        );
         let mut p = Position {
+            size,
             color: [b' '; BOARDSIZE],
             env4: [0; BOARDSIZE],
             env4d: [0; BOARDSIZE],
@@ -89,6 +127,9 @@ impl Position {
             cap: 0,
             cap_x: 0,
             komi: 7.5,
+            handicap: 0,
+            rules: Rules::default(),
+            hash: 0,
         };
         p.clear();
         p
@@ -99,37 +140,55 @@ impl Position {
     /// Black plays on even move numbers (0, 2, 4, ...), White plays on odd move numbers.
     #[inline]
     pub fn is_black_to_play(&self) -> bool {
-        self.n % 2 == 0
+        self.n.is_multiple_of(2)
+    }
+
+    /// One past the last valid board index for this board size.
+    #[inline]
+    pub fn imax(&self) -> usize {
+        board_imax(self.size)
+    }
+
+    /// Maximum game length for this board size.
+    #[inline]
+    pub fn max_game_len(&self) -> usize {
+        max_game_len(self.size)
+    }
+
+    /// Zobrist hash of the stones together with the side to move.
+    #[inline]
+    pub fn situation_hash(&self) -> u64 {
+        if self.is_black_to_play() {
+            self.hash
+        } else {
+            self.hash ^ ZOBRIST[BOARDSIZE][0]
+        }
+    }
+
+    /// Check whether a point lies on this board (passes are not on the board).
+    #[inline]
+    pub fn contains(&self, pt: Point) -> bool {
+        pt != PASS_MOVE && pt < BOARDSIZE && self.color[pt] != OUT
     }
 
     /// Reset a position to the initial empty board state.
     ///
-    /// The board is laid out as a 1D array with padding:
-    /// - Index 0 to N: top padding (out of bounds)
-    /// - Each row: left padding + N playable points
-    /// - Bottom padding
+    /// The board is laid out as a 1D array with padding, rows from the bottom up:
+    /// - Index 0 to W-1: bottom padding (out of bounds)
+    /// - Each row: left padding + `size` playable points + unused columns
+    /// - Everything above row `size`: top padding
     pub fn clear(&mut self) {
-        // Reset to initial position with C padding layout
-        let mut k = 0;
-        for _col in 0..=N {
-            self.color[k] = b' ';
-            k += 1;
-        }
-        for _row in 1..=N {
-            self.color[k] = b' ';
-            k += 1;
-            for _col in 1..=N {
-                self.color[k] = b'.';
-                k += 1;
+        self.color = [OUT; BOARDSIZE];
+        self.env4 = [0; BOARDSIZE];
+        self.env4d = [0; BOARDSIZE];
+        for row in 1..=self.size {
+            for col in 1..=self.size {
+                self.color[row * W + col] = EMPTY;
             }
         }
-        for _col in 0..W {
-            self.color[k] = b' ';
-            k += 1;
-        }
 
         // Initialize env4/env4d arrays
-        for pt in BOARD_IMIN..BOARD_IMAX {
+        for pt in BOARD_IMIN..self.imax() {
             if self.color[pt] == OUT {
                 continue;
             }
@@ -144,6 +203,8 @@ impl Position {
         self.cap = 0;
         self.cap_x = 0;
         self.n = 0;
+        self.handicap = 0;
+        self.hash = 0;
 
         debug_assert!(env4_ok(self), "env4/env4d initialization failed");
     }
@@ -232,46 +293,44 @@ pub fn put_stone(pos: &mut Position, pt: Point) {
     // When a stone is placed, neighbors see this point change from EMPTY to a stone
     //
     // Neighbor layout for env4 updates:
-    // - South neighbor (pt + N+1) sees pt at its North (bit position 0)
-    // - West neighbor (pt - 1) sees pt at its East (bit position 1)
-    // - North neighbor (pt - N-1) sees pt at its South (bit position 2)
-    // - East neighbor (pt + 1) sees pt at its West (bit position 3)
+    // - South neighbor sees pt at its North (bit position 0)
+    // - West neighbor sees pt at its East (bit position 1)
+    // - North neighbor sees pt at its South (bit position 2)
+    // - East neighbor sees pt at its West (bit position 3)
     //
     // For env4d:
-    // - SW neighbor (pt + N) sees pt at its NE (bit position 0)
-    // - NW neighbor (pt - W) sees pt at its SE (bit position 1)
-    // - NE neighbor (pt - N) sees pt at its SW (bit position 2)
-    // - SE neighbor (pt + W) sees pt at its NW (bit position 3)
+    // - SW neighbor sees pt at its NE (bit position 0)
+    // - NW neighbor sees pt at its SE (bit position 1)
+    // - NE neighbor sees pt at its SW (bit position 2)
+    // - SE neighbor sees pt at its NW (bit position 3)
 
-    let pt = pt as isize;
-    let n_plus_1 = (N + 1) as isize;
-    let w = W as isize;
-    let n = N as isize;
+    let [north, east, south, west, ne, se, sw, nw] = all_neighbors(pt);
 
     if pos.is_black_to_play() {
         // BLACK to play (X=BLACK)
         // EMPTY (0b10) -> BLACK (0b01): XOR with 0x11 for position 0, 0x22 for 1, etc.
-        pos.env4[(pt + n_plus_1) as usize] ^= 0x11; // South neighbor
-        pos.env4[(pt - 1) as usize] ^= 0x22; // West neighbor
-        pos.env4[(pt - n_plus_1) as usize] ^= 0x44; // North neighbor
-        pos.env4[(pt + 1) as usize] ^= 0x88; // East neighbor
-        pos.env4d[(pt + n) as usize] ^= 0x11; // SW neighbor
-        pos.env4d[(pt - w) as usize] ^= 0x22; // NW neighbor
-        pos.env4d[(pt - n) as usize] ^= 0x44; // NE neighbor
-        pos.env4d[(pt + w) as usize] ^= 0x88; // SE neighbor
+        pos.env4[south] ^= 0x11;
+        pos.env4[west] ^= 0x22;
+        pos.env4[north] ^= 0x44;
+        pos.env4[east] ^= 0x88;
+        pos.env4d[sw] ^= 0x11;
+        pos.env4d[nw] ^= 0x22;
+        pos.env4d[ne] ^= 0x44;
+        pos.env4d[se] ^= 0x88;
     } else {
         // WHITE to play (X=WHITE)
         // EMPTY (0b10) -> WHITE (0b00): AND with complement to clear high bit
-        pos.env4[(pt + n_plus_1) as usize] &= 0xEE;
-        pos.env4[(pt - 1) as usize] &= 0xDD;
-        pos.env4[(pt - n_plus_1) as usize] &= 0xBB;
-        pos.env4[(pt + 1) as usize] &= 0x77;
-        pos.env4d[(pt + n) as usize] &= 0xEE;
-        pos.env4d[(pt - w) as usize] &= 0xDD;
-        pos.env4d[(pt - n) as usize] &= 0xBB;
-        pos.env4d[(pt + w) as usize] &= 0x77;
-    }
-    pos.color[pt as usize] = STONE_BLACK;
+        pos.env4[south] &= 0xEE;
+        pos.env4[west] &= 0xDD;
+        pos.env4[north] &= 0xBB;
+        pos.env4[east] &= 0x77;
+        pos.env4d[sw] &= 0xEE;
+        pos.env4d[nw] &= 0xDD;
+        pos.env4d[ne] &= 0xBB;
+        pos.env4d[se] &= 0x77;
+    }
+    pos.hash ^= zobrist_key(pt, pos.is_black_to_play());
+    pos.color[pt] = STONE_BLACK;
 }
 
 /// Remove a stone from the board and update env4/env4d arrays incrementally.
@@ -282,35 +341,74 @@ pub fn remove_stone(pos: &mut Position, pt: Point) {
     // Update env4 for orthogonal neighbors
     // When a stone is removed, neighbors see this point change from a stone to EMPTY
 
-    let pt = pt as isize;
-    let n_plus_1 = (N + 1) as isize;
-    let w = W as isize;
-    let n = N as isize;
+    let [north, east, south, west, ne, se, sw, nw] = all_neighbors(pt);
 
     if pos.is_black_to_play() {
         // BLACK to play (x=WHITE)
         // WHITE (0b00) -> EMPTY (0b10): OR with 0x10 for position 0 to set high bit
-        pos.env4[(pt + n_plus_1) as usize] |= 0x10;
-        pos.env4[(pt - 1) as usize] |= 0x20;
-        pos.env4[(pt - n_plus_1) as usize] |= 0x40;
-        pos.env4[(pt + 1) as usize] |= 0x80;
-        pos.env4d[(pt + n) as usize] |= 0x10;
-        pos.env4d[(pt - w) as usize] |= 0x20;
-        pos.env4d[(pt - n) as usize] |= 0x40;
-        pos.env4d[(pt + w) as usize] |= 0x80;
+        pos.env4[south] |= 0x10;
+        pos.env4[west] |= 0x20;
+        pos.env4[north] |= 0x40;
+        pos.env4[east] |= 0x80;
+        pos.env4d[sw] |= 0x10;
+        pos.env4d[nw] |= 0x20;
+        pos.env4d[ne] |= 0x40;
+        pos.env4d[se] |= 0x80;
     } else {
         // WHITE to play (x=BLACK)
         // BLACK (0b01) -> EMPTY (0b10): XOR with 0x11 for each position
-        pos.env4[(pt + n_plus_1) as usize] ^= 0x11;
-        pos.env4[(pt - 1) as usize] ^= 0x22;
-        pos.env4[(pt - n_plus_1) as usize] ^= 0x44;
-        pos.env4[(pt + 1) as usize] ^= 0x88;
-        pos.env4d[(pt + n) as usize] ^= 0x11;
-        pos.env4d[(pt - w) as usize] ^= 0x22;
-        pos.env4d[(pt - n) as usize] ^= 0x44;
-        pos.env4d[(pt + w) as usize] ^= 0x88;
-    }
-    pos.color[pt as usize] = EMPTY;
+        pos.env4[south] ^= 0x11;
+        pos.env4[west] ^= 0x22;
+        pos.env4[north] ^= 0x44;
+        pos.env4[east] ^= 0x88;
+        pos.env4d[sw] ^= 0x11;
+        pos.env4d[nw] ^= 0x22;
+        pos.env4d[ne] ^= 0x44;
+        pos.env4d[se] ^= 0x88;
+    }
+    pos.hash ^= zobrist_key(pt, !pos.is_black_to_play());
+    pos.color[pt] = EMPTY;
+}
+
+// =============================================================================
+// Zobrist Hashing
+// =============================================================================
+
+/// Generate the Zobrist keys at compile time with a fixed splitmix64 sequence,
+/// so hashes are identical across runs and independent of the playout RNG.
+const fn make_zobrist() -> [[u64; 2]; BOARDSIZE + 1] {
+    let mut table = [[0u64; 2]; BOARDSIZE + 1];
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut i = 0;
+    while i < (BOARDSIZE + 1) * 2 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i / 2][i % 2] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// Zobrist keys per point and color (index 0 = White, 1 = Black).
+/// The extra last entry is used as the "White to move" key.
+static ZOBRIST: [[u64; 2]; BOARDSIZE + 1] = make_zobrist();
+
+/// Zobrist key of a stone of the given absolute color at `pt`.
+#[inline]
+fn zobrist_key(pt: Point, black: bool) -> u64 {
+    ZOBRIST[pt][black as usize]
+}
+
+/// Recompute the Zobrist hash of a position from scratch (for verification).
+pub fn compute_hash(pos: &Position) -> u64 {
+    let black_to_play = pos.is_black_to_play();
+    (BOARD_IMIN..pos.imax()).fold(0, |h, pt| match pos.color[pt] {
+        STONE_BLACK => h ^ zobrist_key(pt, black_to_play),
+        STONE_WHITE => h ^ zobrist_key(pt, !black_to_play),
+        _ => h,
+    })
 }
 
 /// Verify that env4/env4d arrays are consistent with the board state.
@@ -319,7 +417,7 @@ pub fn remove_stone(pos: &mut Position, pt: Point) {
 /// and compares with the stored values. Returns true if consistent.
 #[cfg(debug_assertions)]
 pub fn env4_ok(pos: &Position) -> bool {
-    for pt in BOARD_IMIN..BOARD_IMAX {
+    for pt in BOARD_IMIN..pos.imax() {
         if pos.color[pt] == OUT {
             continue;
         }
@@ -440,12 +538,16 @@ pub fn is_eye(pos: &Position, pt: Point) -> u8 {
 /// Play a move at the given point.
 ///
 /// Handles pass moves, legality checking, captures, ko detection, and color swapping.
+/// Suicide is rejected unless `pos.rules.suicide` allows it and the group has
+/// more than one stone, in which case the group is removed. Superko is not
+/// checked here (see [`Superko`]).
 /// Returns `Ok(())` on success, or `Err(MoveError)` on failure.
 ///
 /// # Errors
 /// - `MoveError::Occupied` - if the point is occupied
 /// - `MoveError::Ko` - if the move violates the ko rule
-/// - `MoveError::Suicide` - if the move would have no liberties
+/// - `MoveError::Suicide` - if the move would have no liberties and the rules forbid suicide
+///   (always for a single stone)
 pub fn play_move(pos: &mut Position, pt: Point) -> Result<(), MoveError> {
     if pt == PASS_MOVE {
         pass_move(pos);
@@ -490,6 +592,7 @@ pub fn play_move(pos: &mut Position, pt: Point) -> Result<(), MoveError> {
         remove_stone(pos, r);
     }
 
+    let mut suicide = false;
     if captured > 0 {
         // Set ko if captured exactly one stone in an eye
         if captured == 1 && in_enemy_eye != 0 {
@@ -500,18 +603,20 @@ pub fn play_move(pos: &mut Position, pt: Point) -> Result<(), MoveError> {
     } else {
         // Test for suicide
         pos.ko = 0;
-        if group_liberties(pos, pt) == 0 {
+        suicide = group_liberties(pos, pt) == 0;
+        // A lone stone's suicide would only give back the previous board
+        let lone = neighbors(pt).iter().all(|&n| pos.color[n] != STONE_BLACK);
+        if suicide && (!pos.rules.suicide || lone) {
             // Undo the stone placement (need to restore env4/env4d too)
             pos.color[pt] = EMPTY;
+            pos.hash ^= zobrist_key(pt, pos.is_black_to_play());
             // Restore env4/env4d by recomputing (simpler than inverse of put_stone)
-            for k in 0..4 {
-                let n = (pt as isize + DELTA[k]) as usize;
+            for n in neighbors(pt) {
                 if pos.color[n] != OUT {
                     pos.env4[n] = compute_env4(pos, n, 0);
                 }
             }
-            for k in 4..8 {
-                let n = (pt as isize + DELTA[k]) as usize;
+            for n in diagonal_neighbors(pt) {
                 if pos.color[n] != OUT {
                     pos.env4d[n] = compute_env4(pos, n, 4);
                 }
@@ -532,13 +637,78 @@ pub fn play_move(pos: &mut Position, pt: Point) -> Result<(), MoveError> {
     pos.last2 = pos.last;
     pos.last = pt;
 
+    // Suicide (only reached if the rules allow it): the mover's group, now
+    // 'x', is removed and counts as prisoners for the player to move
+    if suicide {
+        let mut group = Vec::new();
+        pos.cap_x += collect_group(pos, pt, &mut group);
+        for r in group {
+            remove_stone(pos, r);
+        }
+    }
+
     debug_assert!(env4_ok(pos), "env4/env4d inconsistent after play_move");
+    debug_assert_eq!(
+        pos.hash,
+        compute_hash(pos),
+        "hash inconsistent after play_move"
+    );
+    Ok(())
+}
+
+/// Place a stone of the given color without playing a move (SGF setup,
+/// handicap stones).
+///
+/// The move number, side to move, ko and captures are unchanged, and no
+/// stones are captured; the point must be empty.
+pub fn place_stone(pos: &mut Position, pt: Point, black: bool) {
+    debug_assert_eq!(pos.color[pt], EMPTY, "place_stone on a non-empty point");
+    if pos.is_black_to_play() == black {
+        put_stone(pos, pt);
+    } else {
+        // Temporarily make `black` the side to move so put_stone places it
+        swap_color(pos);
+        pos.n += 1;
+        put_stone(pos, pt);
+        pos.n -= 1;
+        swap_color(pos);
+    }
+}
+
+/// Make the given color the side to move.
+///
+/// If it is the other color's turn, an implicit pass is inserted for it, so the
+/// move number parity keeps matching the side to move. Returns true if a pass
+/// was inserted.
+pub fn set_to_play(pos: &mut Position, black: bool) -> bool {
+    if pos.is_black_to_play() == black {
+        return false;
+    }
+    pass_move(pos);
+    true
+}
+
+/// Play a move for the given color, whether or not it is that color's turn.
+///
+/// Used by front-ends (e.g. GTP editing or handicap setup) that may place
+/// several stones of one color in a row. See [`set_to_play`].
+///
+/// # Errors
+/// Same as [`play_move`]; the position is left unchanged on error.
+pub fn play_move_for(pos: &mut Position, pt: Point, black: bool) -> Result<(), MoveError> {
+    if pos.is_black_to_play() == black {
+        return play_move(pos, pt);
+    }
+    let mut next = pos.clone();
+    set_to_play(&mut next, black);
+    play_move(&mut next, pt)?;
+    *pos = next;
     Ok(())
 }
 
 /// Get the 4 orthogonal neighbors (N, E, S, W) of a point.
 #[inline]
-fn neighbors(pt: Point) -> [Point; 4] {
+pub fn neighbors(pt: Point) -> [Point; 4] {
     [
         (pt as isize + DELTA[0]) as usize,
         (pt as isize + DELTA[1]) as usize,
@@ -627,11 +797,9 @@ fn group_liberties(pos: &Position, start: Point) -> u32 {
         if pos.color[pt] == color {
             for n in neighbors(pt) {
                 match pos.color[n] {
-                    EMPTY => {
-                        if !liberty_visited[n] {
-                            liberty_visited[n] = true;
-                            libs += 1;
-                        }
+                    EMPTY if !liberty_visited[n] => {
+                        liberty_visited[n] = true;
+                        libs += 1;
                     }
                     c if c == color && !visited[n] => stack.push(n),
                     _ => {}
@@ -720,15 +888,16 @@ pub fn find_neighbor_blocks_in_atari(pos: &Position, stones: &[Point]) -> Vec<(P
 /// Returns 0 for the first line, 1 for the second line, etc.
 /// Used to skip expensive ladder checks for groups with liberties away from edges.
 #[inline]
-pub fn line_height(pt: Point) -> i32 {
-    let row = pt / W;
-    let col = pt % W;
+pub fn line_height(pos: &Position, pt: Point) -> i32 {
+    let n = pos.size as i32;
+    let row = (pt / W) as i32;
+    let col = (pt % W) as i32;
 
     // Calculate distance from each edge
-    let from_left = col as i32 - 1; // -1 because column 0 is padding
-    let from_right = N as i32 - col as i32;
-    let from_top = row as i32 - 1; // -1 because row 0 is padding
-    let from_bottom = N as i32 - row as i32;
+    let from_left = col - 1; // -1 because column 0 is padding
+    let from_right = n - col;
+    let from_bottom = row - 1; // -1 because row 0 is padding
+    let from_top = n - row;
 
     // Return the minimum distance to any edge (0-indexed, so 0 = first line)
     from_left.min(from_right).min(from_top).min(from_bottom)
@@ -788,8 +957,8 @@ pub fn fix_atari(pos: &Position, pt: Point, singlept_ok: bool) -> Vec<Point> {
 /// - `singlept_ok`: If true, don't try to save single-stone groups
 /// - `twolib_test`: If true, also check groups with 2 liberties for ladder captures
 /// - `twolib_edgeonly`: If true and twolib_test is true, only check ladders when
-///                      both liberties are on the edge (line 0). This optimization
-///                      skips expensive ladder calculations for interior groups.
+///   both liberties are on the edge (line 0). This optimization
+///   skips expensive ladder calculations for interior groups.
 ///
 /// Returns moves that can:
 /// - Capture opponent stones (if the group belongs to opponent)
@@ -815,8 +984,8 @@ pub fn fix_atari_ext(
 /// - `singlept_ok`: If true, don't try to save single-stone groups
 /// - `twolib_test`: If true, also check groups with 2 liberties for ladder captures
 /// - `twolib_edgeonly`: If true and twolib_test is true, only check ladders when
-///                      both liberties are on the edge (line 0). This optimization
-///                      skips expensive ladder calculations for interior groups.
+///   both liberties are on the edge (line 0). This optimization
+///   skips expensive ladder calculations for interior groups.
 ///
 /// Returns:
 /// - `moves`: List of moves that can capture/save the group
@@ -851,7 +1020,7 @@ pub fn fix_atari_with_sizes(
         // Test groups with exactly 2 liberties for ladder captures
         if twolib_test && libs.len() == 2 && group_size > 1 {
             // twolib_edgeonly: skip expensive ladder check unless both libs are on edge
-            if twolib_edgeonly && (line_height(libs[0]) > 0 || line_height(libs[1]) > 0) {
+            if twolib_edgeonly && (line_height(pos, libs[0]) > 0 || line_height(pos, libs[1]) > 0) {
                 return (moves, sizes); // Not on edge, skip ladder check
             }
 
@@ -898,15 +1067,14 @@ pub fn fix_atari_with_sizes(
             //   (C code: slist_size(moves) > 1, i.e., need 2+ moves to skip ladder check)
             // - We get 3+ liberties (definitely safe)
             // - We get exactly 2 liberties but ladder check fails
-            if moves.len() > 1
+            if (moves.len() > 1
                 || new_libs.len() >= 3
-                || read_ladder_attack(&test_pos, lib, &new_libs) == 0
+                || read_ladder_attack(&test_pos, lib, &new_libs) == 0)
+                && !moves.contains(&lib)
             {
-                if !moves.contains(&lib) {
-                    moves.push(lib);
-                    // Use the new (post-escape) group size, matching C behavior
-                    sizes.push(new_stones.len());
-                }
+                moves.push(lib);
+                // Use the new (post-escape) group size, matching C behavior
+                sizes.push(new_stones.len());
             }
         }
     }
@@ -985,7 +1153,7 @@ fn gen_capture_moves_in_set(
         pts.to_vec()
     } else {
         // Check all valid board points (matches C's `allpoints` behavior)
-        (BOARD_IMIN..BOARD_IMAX)
+        (BOARD_IMIN..pos.imax())
             .filter(|&pt| pos.color[pt] != OUT)
             .collect()
     };
@@ -1012,10 +1180,18 @@ fn gen_capture_moves_in_set(
     moves
 }
 
+/// Column labels, A-T skipping 'I' per Go convention (one per column up to 19x19).
+const COL_LABELS: &[u8; MAX_N] = b"ABCDEFGHJKLMNOPQRST";
+
 /// Parse a coordinate string (e.g., "D4", "pass") into a Point.
 ///
 /// Go coordinates use letters A-T (skipping I) for columns and 1-19 for rows.
-/// Returns `PASS_MOVE` for "pass" or invalid input.
+/// Returns `PASS_MOVE` for "pass" or invalid input, including vertices that
+/// lie outside the largest (19x19) board.
+///
+/// Rows are counted from the bottom edge, so the result does not depend on
+/// the board size. Use [`Position::contains`] to check that a parsed point
+/// lies on a particular board.
 pub fn parse_coord(s: &str) -> Point {
     if s.eq_ignore_ascii_case("pass") {
         return PASS_MOVE;
@@ -1027,20 +1203,17 @@ pub fn parse_coord(s: &str) -> Point {
     }
 
     let col_char = bytes[0].to_ascii_uppercase();
-    let mut col = (col_char - b'A' + 1) as usize;
-
-    // Skip 'I' column (Go convention to avoid confusion with 'J')
-    if col_char > b'I' {
-        col -= 1;
-    }
+    let Some(col) = COL_LABELS.iter().position(|&c| c == col_char) else {
+        return PASS_MOVE;
+    };
 
     // Parse row number
-    let row: usize = bytes[1..]
-        .iter()
-        .filter(|b| b.is_ascii_digit())
-        .fold(0, |acc, &b| acc * 10 + (b - b'0') as usize);
+    let row = match std::str::from_utf8(&bytes[1..]).map(str::parse::<usize>) {
+        Ok(Ok(row)) if (1..=MAX_N).contains(&row) => row,
+        _ => return PASS_MOVE,
+    };
 
-    (N - row + 1) * (N + 1) + col
+    row * W + col + 1
 }
 
 /// Convert a Point to a coordinate string (e.g., "D4").
@@ -1051,25 +1224,16 @@ pub fn str_coord(pt: Point) -> String {
         return "pass".into();
     }
 
-    let row = pt / (N + 1);
-    let col = pt % (N + 1);
-
-    // Convert column to letter, skipping 'I'
-    let mut c = (b'@' + col as u8) as char;
-    if c >= 'I' {
-        c = (c as u8 + 1) as char;
-    }
+    let row = pt / W;
+    let col = pt % W;
 
-    format!("{c}{}", N + 1 - row)
+    format!("{}{row}", COL_LABELS[col - 1] as char)
 }
 
 // =============================================================================
 // Board Display (Debug Features)
 // =============================================================================
 
-/// Column labels for board display (skipping 'I' per Go convention).
-const COL_LABELS: &[u8] = b"ABCDEFGHJKLMNOPQRSTUVWXYZ";
-
 /// Convert internal color representation to display character.
 ///
 /// The internal representation uses 'X' for current player and 'x' for opponent.
@@ -1173,17 +1337,17 @@ pub fn format_position_with_owner(
     output.push('\n');
 
     // Board rows (from top to bottom: row N down to row 1)
-    for row in 1..=N {
-        let row_label = N - row + 1;
+    let n = pos.size;
+    for row in (1..=n).rev() {
         // Add row number with proper spacing
-        write!(output, " {:>2} ", row_label).unwrap();
+        write!(output, " {:>2} ", row).unwrap();
 
-        for col in 1..=N {
-            let k = row * (N + 1) + col;
+        for col in 1..=n {
+            let k = row * W + col;
             let c = display_color(pos.color[k], black_to_play);
 
             // Check if this is the last move (mark with parentheses)
-            let prev = if col > 1 { row * (N + 1) + col - 1 } else { 0 };
+            let prev = if col > 1 { k - 1 } else { 0 };
 
             // Opening paren before the stone
             if pos.last != 0 && pos.last == k {
@@ -1198,15 +1362,15 @@ pub fn format_position_with_owner(
         }
 
         // Closing paren after last stone if it's at the end of the row
-        if pos.last != 0 && pos.last == row * (N + 1) + N {
+        if pos.last != 0 && pos.last == row * W + n {
             output.push(')');
         }
 
         // Owner map column (if provided)
         if let Some(omap) = owner_map {
             output.push_str("     ");
-            for col in 1..=N {
-                let k = row * (N + 1) + col;
+            for col in 1..=n {
+                let k = row * W + col;
                 let val = omap[k] as f64;
                 let n = n_sims as f64;
                 let c = if val > 0.6 * n {
@@ -1230,9 +1394,9 @@ pub fn format_position_with_owner(
 
     // Column labels
     output.push_str("    ");
-    for col in 0..N {
+    for &label in &COL_LABELS[..n] {
         output.push(' ');
-        output.push(COL_LABELS[col] as char);
+        output.push(label as char);
     }
     output.push_str(" \n\n");
 
@@ -1264,18 +1428,18 @@ impl std::fmt::Display for Position {
         writeln!(f)?;
 
         // Board rows (from top to bottom: row N down to row 1)
-        for row in 1..=N {
-            let row_label = N - row + 1;
+        let n = self.size;
+        for row in (1..=n).rev() {
             // Add row number with proper spacing
-            write!(f, " {:>2} ", row_label)?;
+            write!(f, " {:>2} ", row)?;
 
-            for col in 1..=N {
-                let k = row * (N + 1) + col;
+            for col in 1..=n {
+                let k = row * W + col;
                 let c = display_color(self.color[k], black_to_play);
 
                 // Check if this is the last move (mark with parentheses)
                 // Note: self.last == 0 means pass or no move, so we check self.last != 0
-                let prev = if col > 1 { row * (N + 1) + col - 1 } else { 0 };
+                let prev = if col > 1 { k - 1 } else { 0 };
 
                 // Opening paren before the stone
                 if self.last != 0 && self.last == k {
@@ -1290,7 +1454,7 @@ impl std::fmt::Display for Position {
             }
 
             // Closing paren after last stone if it's at the end of the row
-            if self.last != 0 && self.last == row * (N + 1) + N {
+            if self.last != 0 && self.last == row * W + n {
                 write!(f, ")")?;
             }
             writeln!(f)?;
@@ -1298,8 +1462,8 @@ impl std::fmt::Display for Position {
 
         // Column labels
         write!(f, "    ")?;
-        for col in 0..N {
-            write!(f, " {}", COL_LABELS[col] as char)?;
+        for &label in &COL_LABELS[..n] {
+            write!(f, " {}", label as char)?;
         }
         writeln!(f)?;
         writeln!(f)
@@ -1323,7 +1487,8 @@ mod tests {
     fn test_empty_position() {
         let pos = Position::new();
         // Check that the center is empty
-        let center = (N / 2 + 1) * (N + 1) + (N / 2 + 1);
+        let n = pos.size;
+        let center = (n / 2 + 1) * W + (n / 2 + 1);
         assert_eq!(pos.color[center], b'.');
         assert_eq!(pos.n, 0);
         assert_eq!(pos.ko, 0);
@@ -1331,11 +1496,11 @@ mod tests {
 
     #[test]
     fn test_parse_str_coord_roundtrip() {
-        let pos = Position::new();
+        let pos = Position::with_size(MAX_N);
         // Test some coordinates
-        for row in 1..=N {
-            for col in 1..=N {
-                let pt = row * (N + 1) + col;
+        for row in 1..=MAX_N {
+            for col in 1..=MAX_N {
+                let pt = row * W + col;
                 if pos.color[pt] == b'.' {
                     let s = str_coord(pt);
                     let parsed = parse_coord(&s);
@@ -1345,6 +1510,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_coord_19x19() {
+        assert_eq!(parse_coord("A1"), W + 1);
+        assert_eq!(parse_coord("t19"), MAX_N * W + MAX_N);
+        assert_eq!(str_coord(parse_coord("J10")), "J10");
+
+        // Invalid columns, rows and vertices beyond 19x19
+        for s in ["I5", "U5", "Z1", "A0", "A20", "A", "5A", "-"] {
+            assert_eq!(parse_coord(s), PASS_MOVE, "{s} should not parse");
+        }
+    }
+
+    #[test]
+    fn test_contains() {
+        let small = Position::new();
+        let full = Position::with_size(MAX_N);
+        assert!(small.contains(parse_coord("J9")));
+        assert!(!small.contains(parse_coord("K9")));
+        assert!(!small.contains(parse_coord("A10")));
+        assert!(full.contains(parse_coord("T19")));
+        assert!(!full.contains(PASS_MOVE));
+    }
+
+    #[test]
+    fn test_line_height() {
+        for size in [9, 13, 19] {
+            let pos = Position::with_size(size);
+            assert_eq!(line_height(&pos, parse_coord("A1")), 0);
+            assert_eq!(line_height(&pos, parse_coord("C3")), 2);
+            // Top-right corner depends on the board size
+            let top_right = size * W + size;
+            assert_eq!(line_height(&pos, top_right), 0);
+            assert_eq!(line_height(&pos, top_right - W - 1), 1);
+        }
+    }
+
     #[test]
     fn test_play_move_basic() {
         let mut pos = Position::new();
@@ -1382,6 +1583,66 @@ mod tests {
             result
         );
     }
+    #[test]
+    fn test_suicide_allowed_by_rules() {
+        // White's A1 stone has a single liberty at B1, which Black surrounds
+        let setup = ["A2", "A1", "B2", "pass", "C1"];
+
+        let mut pos = Position::new();
+        pos.rules = crate::rules::Rules::tromp_taylor();
+        for mv in setup {
+            play_move(&mut pos, parse_coord(mv)).unwrap();
+        }
+        // White B1 leaves the A1-B1 group without liberties and captures nothing
+        play_move(&mut pos, parse_coord("B1")).unwrap();
+        assert_eq!(pos.color[parse_coord("A1")], EMPTY);
+        assert_eq!(pos.color[parse_coord("B1")], EMPTY);
+        assert_eq!(pos.last, parse_coord("B1"));
+        assert!(pos.is_black_to_play());
+        // Black (to move) gets the two suicided stones as prisoners
+        assert_eq!(pos.cap_x, 2);
+        assert_eq!(pos.hash, compute_hash(&pos));
+
+        // The same move is illegal under the default rules
+        let mut pos = Position::new();
+        for mv in setup {
+            play_move(&mut pos, parse_coord(mv)).unwrap();
+        }
+        assert_eq!(
+            play_move(&mut pos, parse_coord("B1")),
+            Err(MoveError::Suicide)
+        );
+
+        // Suicide of a single stone is illegal even when suicide is allowed
+        let mut pos = Position::new();
+        pos.rules = crate::rules::Rules::new_zealand();
+        for mv in ["A2", "E5", "B1"] {
+            play_move(&mut pos, parse_coord(mv)).unwrap();
+        }
+        let before = pos.clone();
+        assert_eq!(
+            play_move(&mut pos, parse_coord("A1")),
+            Err(MoveError::Suicide)
+        );
+        assert_eq!(pos.color, before.color);
+        assert_eq!(pos.hash, before.hash);
+    }
+
+    #[test]
+    fn test_place_stone() {
+        let mut pos = Position::new();
+        place_stone(&mut pos, parse_coord("C3"), true);
+        place_stone(&mut pos, parse_coord("G7"), false);
+        place_stone(&mut pos, parse_coord("D4"), true);
+
+        assert_eq!(pos.n, 0);
+        assert!(pos.is_black_to_play());
+        assert_eq!(pos.color[parse_coord("C3")], STONE_BLACK);
+        assert_eq!(pos.color[parse_coord("G7")], STONE_WHITE);
+        assert!(env4_ok(&pos));
+        assert_eq!(pos.hash, compute_hash(&pos));
+    }
+
     #[test]
     fn test_capture() {
         let mut pos = Position::new();
@@ -1410,6 +1671,81 @@ mod tests {
         assert!(result.is_ok(), "Capture move should be legal");
     }
 
+    #[test]
+    fn test_play_move_for_out_of_turn() {
+        let mut pos = Position::new();
+        play_move_for(&mut pos, parse_coord("C3"), true).unwrap();
+        play_move_for(&mut pos, parse_coord("D4"), true).unwrap();
+
+        // An implicit White pass was inserted between the two Black stones
+        assert_eq!(pos.n, 3);
+        assert!(!pos.is_black_to_play());
+        assert_eq!(pos.last2, PASS_MOVE);
+        // White to play, so Black's stones are the opponent's
+        assert_eq!(pos.color[parse_coord("C3")], STONE_WHITE);
+        assert_eq!(pos.color[parse_coord("D4")], STONE_WHITE);
+
+        // In-turn moves do not insert passes
+        play_move_for(&mut pos, parse_coord("E5"), false).unwrap();
+        assert_eq!(pos.n, 4);
+    }
+
+    #[test]
+    fn test_play_move_for_illegal_leaves_position() {
+        let mut pos = Position::new();
+        play_move(&mut pos, parse_coord("C3")).unwrap();
+        let before = pos.n;
+        assert_eq!(
+            play_move_for(&mut pos, parse_coord("C3"), true),
+            Err(MoveError::Occupied)
+        );
+        assert_eq!(pos.n, before);
+        assert!(!pos.is_black_to_play());
+    }
+
+    #[test]
+    fn test_set_to_play() {
+        let mut pos = Position::new();
+        assert!(!set_to_play(&mut pos, true));
+        assert!(set_to_play(&mut pos, false));
+        assert!(!pos.is_black_to_play());
+        assert_eq!(pos.last, PASS_MOVE);
+    }
+
+    #[test]
+    fn test_hash_incremental() {
+        let mut pos = Position::new();
+        assert_eq!(pos.hash, 0);
+
+        // Capture in the corner: B A2, W A1, B B1 captures A1
+        for mv in ["A2", "A1", "B1"] {
+            play_move(&mut pos, parse_coord(mv)).unwrap();
+            assert_eq!(pos.hash, compute_hash(&pos));
+        }
+        assert_eq!(pos.color[parse_coord("A1")], EMPTY);
+
+        // Suicide is rejected without touching the hash
+        let mut white = pos.clone();
+        let before = white.hash;
+        assert_eq!(
+            play_move(&mut white, parse_coord("A1")),
+            Err(MoveError::Suicide)
+        );
+        assert_eq!(white.hash, before);
+
+        // Same stones reached in a different order give the same hash
+        let mut other = Position::new();
+        for mv in ["B1", "E5", "A2"] {
+            play_move(&mut other, parse_coord(mv)).unwrap();
+        }
+        let mut pos2 = Position::new();
+        for mv in ["A2", "E5", "B1"] {
+            play_move(&mut pos2, parse_coord(mv)).unwrap();
+        }
+        assert_eq!(other.hash, pos2.hash);
+        assert_ne!(other.hash, pos.hash);
+    }
+
     #[test]
     fn test_ko_rule() {
         let pos = Position::new();
@@ -1521,15 +1857,13 @@ mod tests {
 
     #[test]
     fn test_env4_playout_simulation() {
-        use crate::constants::MAX_GAME_LEN;
-
         // Simulate what mcplayout does
         let mut pos = Position::new();
         let mut passes = 0;
 
-        while passes < 2 && pos.n < MAX_GAME_LEN {
+        while passes < 2 && pos.n < pos.max_game_len() {
             let mut found_move = false;
-            for pt in BOARD_IMIN..BOARD_IMAX {
+            for pt in BOARD_IMIN..pos.imax() {
                 if pos.color[pt] != EMPTY {
                     continue;
                 }
@@ -1578,7 +1912,7 @@ mod tests {
         assert!(display.contains("Komi: 7.5"), "Should show komi");
 
         // Check row labels exist
-        for row in 1..=N {
+        for row in 1..=pos.size {
             assert!(
                 display.contains(&format!(" {} ", row)),
                 "Should have row label {}",
@@ -0,0 +1,368 @@
+//! GTP regression suites in GoGui's `.tst` format.
+//!
+//! A suite is a list of GTP commands, one per line, with `#` comments. A
+//! numbered command followed by an expectation line is a test case:
+//!
+//! ```text
+//! debug setpos C1 G7 B2 B1
+//! 20 debug fix_atari B1
+//! #? [1 A1]
+//! ```
+//!
+//! The expected response goes between the brackets as a regular expression
+//! that must match the whole response, as in GoGui (so `0 H6|0 J5` accepts
+//! either move). A leading `!` makes the case pass when the response does
+//! not match, and a `*` after the closing bracket marks a known failure.
+//! Runs of whitespace in the response are collapsed before matching. A
+//! command that fails never passes.
+//!
+//! Commands without an expectation set up the cases that follow. If one of
+//! them fails, the next case fails too, with the setup line reported.
+//!
+//! The commands run in-process on a [`GtpEngine`], one engine per file.
+
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use regex::Regex;
+
+use crate::gtp::GtpEngine;
+
+/// How a test case compared with its expectation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Matched the expectation
+    Pass,
+    /// Did not match the expectation
+    Fail,
+    /// Did not match, as marked with `*`
+    ExpectedFailure,
+    /// Matched although marked with `*`
+    UnexpectedPass,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Outcome::Pass => "passed",
+            Outcome::Fail => "FAILED",
+            Outcome::ExpectedFailure => "failed (expected)",
+            Outcome::UnexpectedPass => "PASSED (unexpected)",
+        })
+    }
+}
+
+/// The result of one test case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseResult {
+    /// Command id
+    pub id: u32,
+    /// Line of the expectation in the file
+    pub line: usize,
+    /// The command, without its id
+    pub command: String,
+    /// The response, prefixed with `? ` if the command failed
+    pub response: String,
+    /// The expectation, as written after `#?`
+    pub expected: String,
+    /// How the response compared with the expectation
+    pub outcome: Outcome,
+    /// The first setup command since the previous case that failed, as
+    /// `line N: command: response`; it fails this case
+    pub setup_failure: Option<String>,
+}
+
+/// Counts of each outcome over some test cases.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub passed: usize,
+    pub failed: usize,
+    pub expected_failures: usize,
+    pub unexpected_passes: usize,
+}
+
+impl Summary {
+    /// Count the outcomes of `results`.
+    pub fn of(results: &[CaseResult]) -> Self {
+        let mut summary = Self::default();
+        for result in results {
+            match result.outcome {
+                Outcome::Pass => summary.passed += 1,
+                Outcome::Fail => summary.failed += 1,
+                Outcome::ExpectedFailure => summary.expected_failures += 1,
+                Outcome::UnexpectedPass => summary.unexpected_passes += 1,
+            }
+        }
+        summary
+    }
+
+    /// Add the counts of `other`.
+    pub fn add(&mut self, other: Summary) {
+        self.passed += other.passed;
+        self.failed += other.failed;
+        self.expected_failures += other.expected_failures;
+        self.unexpected_passes += other.unexpected_passes;
+    }
+
+    /// Whether every case passed or failed as expected.
+    pub fn ok(&self) -> bool {
+        self.failed == 0 && self.unexpected_passes == 0
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} passed, {} failed, {} expected failures, {} unexpected passes",
+            self.passed, self.failed, self.expected_failures, self.unexpected_passes
+        )
+    }
+}
+
+/// An expectation line: `#? [regex]`, optionally negated and marked.
+struct Expectation {
+    pattern: Regex,
+    negated: bool,
+    known_failure: bool,
+}
+
+impl Expectation {
+    /// Parse the part of an expectation line after `#?`.
+    fn parse(rest: &str) -> Result<Self> {
+        let rest = rest.trim();
+        let (body, tail) = rest
+            .strip_prefix('[')
+            .and_then(|r| r.rsplit_once(']'))
+            .ok_or_else(|| anyhow!("expected `#? [response]`"))?;
+        let known_failure = match tail.trim() {
+            "" => false,
+            "*" => true,
+            other => bail!("unexpected `{other}` after the expected response"),
+        };
+        let (text, negated) = match body.strip_prefix('!') {
+            Some(text) => (text, true),
+            None => (body, false),
+        };
+        let pattern = Regex::new(&format!("^(?:{text})$"))
+            .with_context(|| format!("bad regular expression `{text}`"))?;
+        Ok(Self {
+            pattern,
+            negated,
+            known_failure,
+        })
+    }
+
+    /// Whether a response of a successful command matches.
+    fn matches(&self, response: &str) -> bool {
+        self.pattern.is_match(&normalize(response)) != self.negated
+    }
+}
+
+/// Collapse runs of whitespace into single spaces.
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A command that ran, waiting for a possible expectation.
+struct Command {
+    line: usize,
+    id: Option<u32>,
+    text: String,
+    success: bool,
+    response: String,
+}
+
+impl Command {
+    /// How a failed setup command is reported.
+    fn failure(&self) -> String {
+        format!("line {}: {}: {}", self.line, self.text, self.response)
+    }
+}
+
+/// Run the commands of a suite on `engine`, returning the result of each
+/// test case. Processing stops at `quit`.
+///
+/// A failed setup command with no case after it is an error.
+pub fn run_script(engine: &mut GtpEngine, script: &str) -> Result<Vec<CaseResult>> {
+    let mut results = Vec::new();
+    let mut last: Option<Command> = None;
+    // The first failed setup command since the previous case
+    let mut setup_failure: Option<String> = None;
+
+    for (i, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("#?") {
+            let Some(Command {
+                id: Some(id),
+                text: command,
+                success,
+                response,
+                ..
+            }) = last.take()
+            else {
+                bail!("line {}: expectation without a numbered command", i + 1);
+            };
+            let expected = Expectation::parse(rest).with_context(|| format!("line {}", i + 1))?;
+            let setup_failure = setup_failure.take();
+            let outcome = match (
+                success && expected.matches(&response),
+                expected.known_failure,
+            ) {
+                _ if setup_failure.is_some() => Outcome::Fail,
+                (true, false) => Outcome::Pass,
+                (false, false) => Outcome::Fail,
+                (false, true) => Outcome::ExpectedFailure,
+                (true, true) => Outcome::UnexpectedPass,
+            };
+            results.push(CaseResult {
+                id,
+                line: i + 1,
+                command,
+                response: if success {
+                    response
+                } else {
+                    format!("? {response}")
+                },
+                expected: rest.trim().to_string(),
+                outcome,
+                setup_failure,
+            });
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        // The previous command had no expectation, so it was setup
+        if let Some(prev) = last.take().filter(|c| !c.success) {
+            setup_failure.get_or_insert_with(|| prev.failure());
+        }
+        let (id, command) = GtpEngine::parse_id(line);
+        let (success, response) = engine.execute_line(command);
+        last = Some(Command {
+            line: i + 1,
+            id,
+            text: command.to_string(),
+            success,
+            response,
+        });
+        if command.split_whitespace().next() == Some("quit") {
+            break;
+        }
+    }
+    if let Some(prev) = last.filter(|c| !c.success) {
+        setup_failure.get_or_insert_with(|| prev.failure());
+    }
+    match setup_failure {
+        Some(failure) => Err(anyhow!("setup failed with no case after it: {failure}")),
+        None => Ok(results),
+    }
+}
+
+/// Run the suite in the file at `path` on `engine`.
+pub fn run_file(engine: &mut GtpEngine, path: &Path) -> Result<Vec<CaseResult>> {
+    let script =
+        std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    run_script(engine, &script).with_context(|| format!("in {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expectations() {
+        let exp = Expectation::parse(" [0 H6|0 J5]").unwrap();
+        assert!(exp.matches("0 H6") && exp.matches(" 0  J5 ") && !exp.matches("0"));
+        assert!(!exp.known_failure);
+
+        // The whole response must match the regular expression
+        let exp = Expectation::parse("[1( [A-T][0-9]+)*]").unwrap();
+        assert!(exp.matches("1") && exp.matches("1 A3 B1"));
+        assert!(!exp.matches("0 A3") && !exp.matches("1 A3 x"));
+        assert!(Expectation::parse("[(]").is_err());
+
+        let exp = Expectation::parse("[!pass]*").unwrap();
+        assert!(exp.matches("D4") && !exp.matches("pass"));
+        assert!(exp.known_failure);
+
+        assert!(Expectation::parse("[]").unwrap().matches(""));
+        assert!(Expectation::parse("pass").is_err());
+        assert!(Expectation::parse("[pass] x").is_err());
+    }
+
+    #[test]
+    fn test_run_script() {
+        let script = "\
+# setup
+boardsize 9
+10 name
+#? [michi-rust]
+20 play b D4
+#? [ok]*
+30 debug setpos
+#? []*
+play b Z9
+40 known_command bogus
+#? [false]
+45 known_command name
+#? [true]
+50 play b D4
+#? [!x]
+60 quit
+70 name
+#? [michi-rust]
+";
+        let mut engine = GtpEngine::with_simulations(10);
+        let results = run_script(&mut engine, script).unwrap();
+        let outcomes: Vec<(u32, Outcome)> = results.iter().map(|r| (r.id, r.outcome)).collect();
+        assert_eq!(
+            outcomes,
+            [
+                (10, Outcome::Pass),
+                (20, Outcome::ExpectedFailure),
+                (30, Outcome::UnexpectedPass),
+                (40, Outcome::Fail),
+                (45, Outcome::Pass),
+                (50, Outcome::Fail),
+            ]
+        );
+        assert_eq!(results[5].response, "? illegal move");
+        assert_eq!(results[1].line, 6);
+
+        // The failed setup line fails the case after it, and only that one
+        assert_eq!(
+            results[3].setup_failure.as_deref(),
+            Some("line 9: play b Z9: invalid vertex")
+        );
+        assert_eq!(results[3].response, "false");
+        assert_eq!(results[4].setup_failure, None);
+
+        let summary = Summary::of(&results);
+        assert_eq!(
+            summary.to_string(),
+            "2 passed, 2 failed, 1 expected failures, 1 unexpected passes"
+        );
+        assert!(!summary.ok());
+
+        let err = run_script(&mut engine, "name\n#? [michi-rust]\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+
+        // A numbered command without an expectation is setup too
+        let script = "10 play b Z9\n20 name\n#? [michi-rust]\n";
+        let results = run_script(&mut engine, script).unwrap();
+        assert_eq!(results[0].outcome, Outcome::Fail);
+        assert!(
+            results[0]
+                .setup_failure
+                .as_ref()
+                .unwrap()
+                .starts_with("line 1:")
+        );
+
+        let err = run_script(&mut engine, "clear_board\nplay b Z9\n").unwrap_err();
+        assert!(err.to_string().contains("line 2: play b Z9"), "got: {err}");
+    }
+}
@@ -0,0 +1,259 @@
+//! Rule sets: scoring method, suicide, superko and handicap compensation.
+//!
+//! The engine plays under Chinese rules by default. A [`Rules`] value is
+//! stored on each [`Position`] so that move legality (`play_move`) and
+//! scoring (`playout::score`) follow the rule set chosen for the game.
+//!
+//! | Preset         | Scoring      | Suicide | Superko     | Handicap komi |
+//! |----------------|--------------|---------|-------------|---------------|
+//! | `chinese`      | Area         | no      | positional  | N             |
+//! | `japanese`     | Territory    | no      | off         | none          |
+//! | `aga`          | Area         | no      | situational | N - 1         |
+//! | `new_zealand`  | Area         | yes     | situational | N             |
+//! | `tromp-taylor` | Tromp-Taylor | yes     | positional  | none          |
+
+use crate::position::Position;
+
+/// How the final position is counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scoring {
+    /// Stones plus surrounded empty points (Chinese style).
+    /// Single empty points count for the color around them.
+    #[default]
+    Area,
+    /// Surrounded empty points plus prisoners (Japanese style)
+    Territory,
+    /// Stones plus empty regions that reach only one color
+    TrompTaylor,
+}
+
+/// Superko rule: which repetitions of an earlier position make a move illegal.
+///
+/// Simple ko (`Position::ko`) is always enforced; superko additionally
+/// forbids longer cycles such as triple ko. It needs the game history, so it
+/// is checked by the caller (e.g. the GTP engine) rather than by `play_move`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Superko {
+    /// Only simple ko is enforced
+    Off,
+    /// The same arrangement of stones may not appear twice
+    #[default]
+    Positional,
+    /// The same arrangement of stones with the same side to move may not appear twice
+    Situational,
+}
+
+impl Superko {
+    /// Parse a rule name ("off", "positional" or "situational").
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "off" | "none" => Some(Superko::Off),
+            "positional" => Some(Superko::Positional),
+            "situational" => Some(Superko::Situational),
+            _ => None,
+        }
+    }
+
+    /// The rule name, as accepted by [`Superko::from_name`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Superko::Off => "off",
+            Superko::Positional => "positional",
+            Superko::Situational => "situational",
+        }
+    }
+
+    /// The key under which `pos` is compared with earlier positions,
+    /// or `None` if superko is off.
+    pub fn key(self, pos: &Position) -> Option<u64> {
+        match self {
+            Superko::Off => None,
+            Superko::Positional => Some(pos.hash),
+            Superko::Situational => Some(pos.situation_hash()),
+        }
+    }
+}
+
+/// Extra komi White receives for Black's handicap stones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HandicapKomi {
+    /// No compensation
+    None,
+    /// One point per handicap stone
+    #[default]
+    PerStone,
+    /// One point per handicap stone after the first (AGA)
+    PerStoneAfterFirst,
+}
+
+impl HandicapKomi {
+    /// Compensation for `handicap` stones.
+    pub fn bonus(self, handicap: usize) -> f32 {
+        match self {
+            HandicapKomi::None => 0.0,
+            HandicapKomi::PerStone => handicap as f32,
+            HandicapKomi::PerStoneAfterFirst => handicap.saturating_sub(1) as f32,
+        }
+    }
+}
+
+/// A rule set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rules {
+    /// How the final position is counted
+    pub scoring: Scoring,
+    /// Whether suicide of more than one stone is a legal move (suicide of a
+    /// single stone never is)
+    pub suicide: bool,
+    /// Which board repetitions are forbidden
+    pub superko: Superko,
+    /// Extra komi for handicap games
+    pub handicap_komi: HandicapKomi,
+}
+
+impl Rules {
+    /// Chinese rules (the default).
+    pub fn chinese() -> Self {
+        Self::default()
+    }
+
+    /// Japanese rules.
+    pub fn japanese() -> Self {
+        Rules {
+            scoring: Scoring::Territory,
+            suicide: false,
+            superko: Superko::Off,
+            handicap_komi: HandicapKomi::None,
+        }
+    }
+
+    /// AGA rules.
+    pub fn aga() -> Self {
+        Rules {
+            scoring: Scoring::Area,
+            suicide: false,
+            superko: Superko::Situational,
+            handicap_komi: HandicapKomi::PerStoneAfterFirst,
+        }
+    }
+
+    /// New Zealand rules.
+    pub fn new_zealand() -> Self {
+        Rules {
+            scoring: Scoring::Area,
+            suicide: true,
+            superko: Superko::Situational,
+            handicap_komi: HandicapKomi::PerStone,
+        }
+    }
+
+    /// Tromp-Taylor rules.
+    pub fn tromp_taylor() -> Self {
+        Rules {
+            scoring: Scoring::TrompTaylor,
+            suicide: true,
+            superko: Superko::Positional,
+            handicap_komi: HandicapKomi::None,
+        }
+    }
+
+    /// Look up a preset by name, as used by the GTP `kgs-rules` command.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace('-', "_").as_str() {
+            "chinese" => Some(Self::chinese()),
+            "japanese" => Some(Self::japanese()),
+            "aga" => Some(Self::aga()),
+            "new_zealand" | "nz" => Some(Self::new_zealand()),
+            "tromp_taylor" | "tt" => Some(Self::tromp_taylor()),
+            _ => None,
+        }
+    }
+
+    /// The SGF `RU` name of this rule set, if it is one of the presets.
+    pub fn sgf_name(&self) -> Option<&'static str> {
+        [
+            (Self::chinese(), "Chinese"),
+            (Self::japanese(), "Japanese"),
+            (Self::aga(), "AGA"),
+            (Self::new_zealand(), "NZ"),
+            (Self::tromp_taylor(), "Tromp-Taylor"),
+        ]
+        .into_iter()
+        .find(|(rules, _)| rules == self)
+        .map(|(_, name)| name)
+    }
+
+    /// Komi plus the handicap compensation for this rule set.
+    pub fn effective_komi(&self, pos: &Position) -> f32 {
+        pos.komi + self.handicap_komi.bonus(pos.handicap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_by_name() {
+        assert_eq!(Rules::from_name("Chinese"), Some(Rules::default()));
+        assert_eq!(Rules::from_name("japanese"), Some(Rules::japanese()));
+        assert_eq!(
+            Rules::from_name("tromp-taylor"),
+            Some(Rules::tromp_taylor())
+        );
+        assert_eq!(
+            Rules::from_name("new_zealand").map(|r| r.suicide),
+            Some(true)
+        );
+        assert_eq!(Rules::from_name("ing"), None);
+
+        // SGF names round-trip through from_name
+        for rules in [
+            Rules::chinese(),
+            Rules::aga(),
+            Rules::new_zealand(),
+            Rules::tromp_taylor(),
+        ] {
+            assert_eq!(Rules::from_name(rules.sgf_name().unwrap()), Some(rules));
+        }
+        let mut custom = Rules::japanese();
+        custom.suicide = true;
+        assert_eq!(custom.sgf_name(), None);
+    }
+
+    #[test]
+    fn test_handicap_komi() {
+        let mut pos = Position::new();
+        pos.komi = 0.5;
+        pos.handicap = 4;
+        assert_eq!(Rules::chinese().effective_komi(&pos), 4.5);
+        assert_eq!(Rules::aga().effective_komi(&pos), 3.5);
+        assert_eq!(Rules::japanese().effective_komi(&pos), 0.5);
+    }
+
+    #[test]
+    fn test_superko_keys() {
+        use crate::position::{parse_coord, pass_move, play_move};
+
+        let mut pos = Position::new();
+        play_move(&mut pos, parse_coord("E5")).unwrap();
+        let mut passed = pos.clone();
+        pass_move(&mut passed);
+
+        // A pass keeps the stones but changes the side to move
+        assert_eq!(
+            Superko::Positional.key(&pos),
+            Superko::Positional.key(&passed)
+        );
+        assert_ne!(
+            Superko::Situational.key(&pos),
+            Superko::Situational.key(&passed)
+        );
+        assert_eq!(Superko::Off.key(&pos), None);
+        assert_eq!(
+            Superko::from_name("Situational"),
+            Some(Superko::Situational)
+        );
+        assert_eq!(Superko::from_name("chinese"), None);
+    }
+}
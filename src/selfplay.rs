@@ -0,0 +1,168 @@
+//! Self-play: complete engine-vs-engine games.
+//!
+//! [`play_game`] plays both sides with [`tree_search`], following the rules
+//! `genmove` plays by:
+//! - A player passes after the opponent's pass once past the opening
+//! - A player resigns when its best move (other than a pass) wins less than
+//!   `resign_thres`
+//! - Moves that would repeat an earlier position under the superko rule are
+//!   not searched
+//!
+//! The tree is kept from move to move. A game ends with two passes, a
+//! resignation or at the maximum game length; otherwise it is scored like
+//! `final_score`, with dead stones estimated by playouts. The resulting
+//! [`GameRecord`] converts to SGF with the winrate of every searched move as
+//! a comment, for building datasets and spotting regressions.
+
+use std::collections::HashSet;
+
+use fastrand::Rng;
+
+use crate::config::SearchConfig;
+use crate::constants::{BOARDSIZE, PASS_MOVE, RESIGN_MOVE};
+use crate::gtp::GtpEngine;
+use crate::mcts::{
+    GroupStatus, Tree, estimate_ownership, expand_root_with, group_statuses, tree_search,
+};
+use crate::playout::final_score;
+use crate::position::{Point, Position, pass_move, play_move};
+use crate::sgf::write_sgf_game;
+
+/// A finished self-play game.
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    /// Positions of the game, from the initial one
+    pub positions: Vec<Position>,
+    /// Comment on the move leading to each position after the first
+    pub comments: Vec<String>,
+    /// Outcome in SGF's `RE` format (`B+3.5`, `W+R`, `0` for a draw)
+    pub result: String,
+}
+
+impl GameRecord {
+    /// Number of moves played, passes included.
+    pub fn moves(&self) -> usize {
+        self.positions.len() - 1
+    }
+
+    /// The game as SGF, with its result and move comments.
+    pub fn to_sgf(&self) -> String {
+        write_sgf_game(&self.positions, &self.comments, Some(&self.result))
+    }
+}
+
+/// Play a game from `start` with `sims` simulations per move, drawing the
+/// search's random choices from `rng`.
+pub fn play_game(
+    start: &Position,
+    sims: usize,
+    config: &SearchConfig,
+    rng: &mut Rng,
+) -> GameRecord {
+    let mut pos = start.clone();
+    let mut positions = vec![pos.clone()];
+    let mut comments = Vec::new();
+    let mut seen: HashSet<u64> = pos.rules.superko.key(&pos).into_iter().collect();
+    let mut tree: Option<Tree> = None;
+    let mut passes = 0;
+
+    while passes < 2 && pos.n < pos.max_game_len() {
+        let comment = if pos.last == PASS_MOVE && pos.n > 2 {
+            pass_move(&mut pos);
+            "pass after the opponent's pass".to_string()
+        } else {
+            let mut t = match tree.take() {
+                Some(t) if t.is_at(&pos) => t,
+                _ => {
+                    let mut t = Tree::new(&pos);
+                    *t.rng_mut() = rng.fork();
+                    t
+                }
+            };
+            let superko = pos.rules.superko;
+            expand_root_with(&mut t, config, |p| {
+                superko.key(p).is_none_or(|k| !seen.contains(&k))
+            });
+            let result = tree_search(&mut t, sims, config);
+
+            let pt = result.best_move;
+            if result.winrate < config.resign_thres && pt != PASS_MOVE {
+                let winner = if pos.is_black_to_play() { 'W' } else { 'B' };
+                return GameRecord {
+                    positions,
+                    comments,
+                    result: format!("{winner}+R"),
+                };
+            }
+            if pt == PASS_MOVE || pt == RESIGN_MOVE {
+                pass_move(&mut pos);
+            } else {
+                play_move(&mut pos, pt).expect("the search returned an illegal move");
+            }
+            tree = t.into_child(&pos);
+            format!(
+                "winrate {:.3} after {} simulations",
+                result.winrate, result.sims
+            )
+        };
+
+        passes = if pos.last == PASS_MOVE { passes + 1 } else { 0 };
+        seen.extend(pos.rules.superko.key(&pos));
+        positions.push(pos.clone());
+        comments.push(comment);
+    }
+
+    let result = GtpEngine::format_result(black_score(&pos, sims, config, rng));
+    GameRecord {
+        positions,
+        comments,
+        result,
+    }
+}
+
+/// The score of `pos` from Black's point of view, with the stones that
+/// playouts find dead removed.
+fn black_score(pos: &Position, sims: usize, config: &SearchConfig, rng: &mut Rng) -> f64 {
+    let mut owner_map = vec![0i32; BOARDSIZE];
+    estimate_ownership(pos, sims, config, &mut owner_map, rng);
+    let dead: Vec<Point> = group_statuses(pos, &owner_map, sims)
+        .into_iter()
+        .filter(|(_, status)| *status == GroupStatus::Dead)
+        .flat_map(|(stones, _)| stones)
+        .collect();
+    let s = final_score(pos, &dead);
+    if pos.is_black_to_play() { s } else { -s }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sgf::read_sgf;
+
+    #[test]
+    fn test_play_game() {
+        let mut start = Position::with_size(5);
+        start.komi = 0.5;
+        let record = play_game(&start, 30, &SearchConfig::default(), &mut Rng::with_seed(3));
+
+        assert!(record.moves() > 0);
+        assert_eq!(record.comments.len(), record.moves());
+        assert!(record.comments[0].starts_with("winrate "));
+        let last = record.positions.last().unwrap();
+        assert!(
+            record.result.ends_with("+R")
+                || last.last == PASS_MOVE
+                || last.n >= last.max_game_len()
+        );
+
+        // The SGF replays to the same final position
+        let sgf = record.to_sgf();
+        assert!(sgf.contains(&format!("RE[{}]", record.result)));
+        let replayed = read_sgf(&sgf).unwrap();
+        assert_eq!(replayed.last().unwrap().color, last.color);
+
+        // The same seed plays the same game
+        let again = play_game(&start, 30, &SearchConfig::default(), &mut Rng::with_seed(3));
+        assert_eq!(again.to_sgf(), sgf);
+    }
+}
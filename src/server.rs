@@ -0,0 +1,57 @@
+//! GTP over TCP.
+//!
+//! [`serve`] accepts connections on a listening socket and runs a GTP
+//! session on each, on its own thread and with its own [`GtpEngine`], so
+//! that every connection plays its own game. This is how match runners and
+//! server bridges that talk to engines over the network connect.
+//!
+//! A session ends with `quit` or when the client closes the connection.
+
+use std::io::BufReader;
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::gtp::GtpEngine;
+
+/// Serve GTP on every connection accepted by `listener`, each with a fresh
+/// engine from `new_engine`. Runs forever.
+///
+/// Failed connections are reported on stderr and do not stop the server.
+pub fn serve<F>(listener: TcpListener, new_engine: F)
+where
+    F: Fn() -> GtpEngine + Send + Sync + 'static,
+{
+    let new_engine = Arc::new(new_engine);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("michi-rust: accept failed: {e}");
+                continue;
+            }
+        };
+        let new_engine = Arc::clone(&new_engine);
+        std::thread::spawn(move || {
+            let peer = stream
+                .peer_addr()
+                .map_or_else(|_| "unknown peer".to_string(), |a| a.to_string());
+            eprintln!("michi-rust: connection from {peer}");
+            match serve_connection(&mut new_engine(), stream) {
+                Ok(()) => eprintln!("michi-rust: {peer} disconnected"),
+                Err(e) => eprintln!("michi-rust: {peer}: {e:#}"),
+            }
+        });
+    }
+}
+
+/// Run a GTP session with `engine` on `stream` until `quit` or the end of
+/// the input, then close the connection.
+pub fn serve_connection(engine: &mut GtpEngine, stream: TcpStream) -> Result<()> {
+    let input = BufReader::new(stream.try_clone()?);
+    let result = engine.run_with(input, &stream);
+    // Closing both ways ends the engine's reader thread after a `quit`
+    let _ = stream.shutdown(Shutdown::Both);
+    result
+}
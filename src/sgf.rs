@@ -0,0 +1,626 @@
+//! Smart Game Format (SGF) reading and writing.
+//!
+//! SGF is the standard file format for Go game records. This module parses
+//! the first game of an SGF collection into a tree of nodes, replays its main
+//! line (the first variation at every branch) into a sequence of positions,
+//! and writes a sequence of positions back out as a game record.
+//!
+//! ## Supported Properties
+//!
+//! - `SZ` - Board size (square boards from 2 to 19)
+//! - `KM` - Komi
+//! - `HA` - Number of handicap stones
+//! - `RU` - Rule set (see [`Rules::from_name`])
+//! - `AB` / `AW` / `AE` - Setup stones (compressed point lists allowed)
+//! - `PL` - Side to move
+//! - `B` / `W` - Moves (`[]` or `[tt]` for pass)
+//!
+//! Other properties are parsed and kept in the tree but otherwise ignored.
+//!
+//! ## Example
+//!
+//! ```
+//! use michi_rust::sgf::{read_sgf, write_sgf};
+//!
+//! let positions = read_sgf("(;SZ[9]KM[6.5];B[ee];W[cc])").unwrap();
+//! assert_eq!(positions.len(), 3);
+//! assert_eq!(positions[2].komi, 6.5);
+//!
+//! let sgf = write_sgf(&positions);
+//! assert!(sgf.contains(";B[ee];W[cc]"));
+//! ```
+
+use std::collections::HashSet;
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::constants::{BOARD_IMIN, EMPTY, MAX_N, MIN_N, PASS_MOVE, STONE_BLACK, STONE_WHITE, W};
+use crate::position::{
+    Point, Position, pass_move, place_stone, play_move, play_move_for, remove_stone, set_to_play,
+};
+use crate::rules::Rules;
+
+/// A node of an SGF game tree.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SgfNode {
+    /// Properties in file order: identifier and its values
+    pub props: Vec<(String, Vec<String>)>,
+    /// Variations; the first child continues the main line
+    pub children: Vec<SgfNode>,
+}
+
+impl SgfNode {
+    /// All values of a property (empty if absent).
+    pub fn values(&self, id: &str) -> &[String] {
+        self.props
+            .iter()
+            .find(|(pid, _)| pid == id)
+            .map(|(_, values)| values.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// The first value of a property.
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.values(id).first().map(String::as_str)
+    }
+
+    /// The main line: this node followed by the first child at every branch.
+    pub fn main_line(&self) -> impl Iterator<Item = &SgfNode> {
+        std::iter::successors(Some(self), |node| node.children.first())
+    }
+}
+
+// =============================================================================
+// Parsing
+// =============================================================================
+
+/// Parse the first game tree of an SGF collection, returning its root node.
+pub fn parse_sgf(text: &str) -> Result<SgfNode> {
+    let mut parser = Parser {
+        bytes: text.as_bytes(),
+        i: 0,
+    };
+    parser.skip_ws();
+    if !parser.eat(b'(') {
+        bail!("SGF must start with '('");
+    }
+    parser.game_tree()
+}
+
+/// Recursive-descent parser over the raw SGF bytes.
+struct Parser<'a> {
+    bytes: &'a [u8],
+    i: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.i).copied()
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        if self.peek() == Some(b) {
+            self.i += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.i += 1;
+        }
+    }
+
+    /// Parse a game tree after its opening '(' up to and including its ')'.
+    /// Returns the first node of its sequence, with the rest chained as
+    /// first children and the sub-trees as further children of the last node.
+    fn game_tree(&mut self) -> Result<SgfNode> {
+        let mut sequence = Vec::new();
+        let mut variations = Vec::new();
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                Some(b';') => {
+                    self.i += 1;
+                    sequence.push(self.node()?);
+                }
+                Some(b'(') => {
+                    self.i += 1;
+                    variations.push(self.game_tree()?);
+                }
+                Some(b')') => {
+                    self.i += 1;
+                    break;
+                }
+                Some(c) => bail!("unexpected '{}' at byte {}", c as char, self.i),
+                None => bail!("unterminated game tree"),
+            }
+        }
+
+        let mut node = sequence
+            .pop()
+            .ok_or_else(|| anyhow!("empty game tree at byte {}", self.i))?;
+        node.children = variations;
+        while let Some(mut parent) = sequence.pop() {
+            parent.children.push(node);
+            node = parent;
+        }
+        Ok(node)
+    }
+
+    /// Parse the properties of a node (after its ';').
+    fn node(&mut self) -> Result<SgfNode> {
+        let mut node = SgfNode::default();
+        loop {
+            self.skip_ws();
+            // Property identifiers are upper case; FF[3] style lower case
+            // letters (e.g. "AddBlack") are skipped
+            let mut id = String::new();
+            while let Some(c) = self.peek().filter(u8::is_ascii_alphabetic) {
+                if c.is_ascii_uppercase() {
+                    id.push(c as char);
+                }
+                self.i += 1;
+            }
+            if id.is_empty() {
+                return Ok(node);
+            }
+
+            let mut values = Vec::new();
+            self.skip_ws();
+            while self.eat(b'[') {
+                values.push(self.value()?);
+                self.skip_ws();
+            }
+            if values.is_empty() {
+                bail!("property {id} without value");
+            }
+            node.props.push((id, values));
+        }
+    }
+
+    /// Parse a property value (after its '['), handling '\' escapes.
+    fn value(&mut self) -> Result<String> {
+        let mut value = Vec::new();
+        loop {
+            match self.peek() {
+                Some(b']') => {
+                    self.i += 1;
+                    return String::from_utf8(value).context("property value is not UTF-8");
+                }
+                Some(b'\\') => {
+                    self.i += 1;
+                    if let Some(c) = self.peek() {
+                        value.push(c);
+                        self.i += 1;
+                    }
+                }
+                Some(c) => {
+                    value.push(c);
+                    self.i += 1;
+                }
+                None => bail!("unterminated property value"),
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Replaying a game
+// =============================================================================
+
+/// Convert an SGF point ("cd") to a board point; `None` for a pass.
+fn sgf_point(value: &str, size: usize) -> Result<Option<Point>> {
+    let b = value.as_bytes();
+    if b.is_empty() || (value == "tt" && size <= 19) {
+        return Ok(None);
+    }
+    let valid = |c: u8| c.is_ascii_lowercase() && ((c - b'a') as usize) < size;
+    if b.len() != 2 || !valid(b[0]) || !valid(b[1]) {
+        bail!("invalid SGF point '{value}' on {size}x{size}");
+    }
+    let col = (b[0] - b'a') as usize + 1;
+    let row = size - (b[1] - b'a') as usize;
+    Ok(Some(row * W + col))
+}
+
+/// Expand a list of points, including compressed "aa:cc" rectangles.
+fn sgf_points(values: &[String], size: usize) -> Result<Vec<Point>> {
+    let mut points = Vec::new();
+    for value in values {
+        let Some((from, to)) = value.split_once(':') else {
+            points.extend(sgf_point(value, size)?);
+            continue;
+        };
+        let (Some(a), Some(b)) = (sgf_point(from, size)?, sgf_point(to, size)?) else {
+            bail!("invalid SGF rectangle '{value}'");
+        };
+        for row in (a / W).min(b / W)..=(a / W).max(b / W) {
+            for col in (a % W).min(b % W)..=(a % W).max(b % W) {
+                points.push(row * W + col);
+            }
+        }
+    }
+    Ok(points)
+}
+
+/// Replay the main line of a game tree.
+///
+/// Returns the position after the root node's setup, followed by the
+/// position after each move. Setup properties in later nodes modify the
+/// current position without adding an entry, except that a `PL` changing
+/// the side to move adds the implicit pass it stands for.
+///
+/// # Errors
+/// Fails on unsupported board sizes, malformed values and illegal moves.
+pub fn load_positions(root: &SgfNode) -> Result<Vec<Position>> {
+    let size = match root.get("SZ") {
+        Some(sz) => sz
+            .trim()
+            .parse::<usize>()
+            .with_context(|| format!("unsupported board size '{sz}'"))?,
+        None => 19,
+    };
+    if !(MIN_N..=MAX_N).contains(&size) {
+        bail!("unsupported board size {size}");
+    }
+
+    let mut pos = Position::with_size(size);
+    if let Some(km) = root.get("KM") {
+        pos.komi = km
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid komi '{km}'"))?;
+    }
+    if let Some(ha) = root.get("HA") {
+        pos.handicap = ha
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid handicap '{ha}'"))?;
+    }
+    if let Some(rules) = root.get("RU").and_then(Rules::from_name) {
+        pos.rules = rules;
+    }
+
+    let mut positions = Vec::new();
+    for (i, node) in root.main_line().enumerate() {
+        // Setup stones
+        for (id, black) in [("AB", true), ("AW", false)] {
+            for pt in sgf_points(node.values(id), size)? {
+                if pos.color[pt] != EMPTY {
+                    clear_point(&mut pos, pt);
+                }
+                place_stone(&mut pos, pt, black);
+            }
+        }
+        for pt in sgf_points(node.values("AE"), size)? {
+            clear_point(&mut pos, pt);
+        }
+        // A change of the side to move is an implicit pass
+        let mut moved = match node.get("PL") {
+            Some(pl) => set_to_play(&mut pos, pl.eq_ignore_ascii_case("b")),
+            None => false,
+        };
+
+        // Moves
+        for (id, black) in [("B", true), ("W", false)] {
+            let Some(value) = node.get(id) else { continue };
+            match sgf_point(value, size)? {
+                Some(pt) => play_move_for(&mut pos, pt, black)
+                    .map_err(|e| anyhow!("move {id}[{value}]: {e}"))?,
+                None => {
+                    set_to_play(&mut pos, black);
+                    pass_move(&mut pos);
+                }
+            }
+            moved = true;
+        }
+        if i == 0 || moved {
+            positions.push(pos.clone());
+        } else if let Some(last) = positions.last_mut() {
+            *last = pos.clone();
+        }
+    }
+
+    Ok(positions)
+}
+
+/// Remove a stone of either color placed on `pt`.
+fn clear_point(pos: &mut Position, pt: Point) {
+    match pos.color[pt] {
+        // remove_stone removes opponent ('x') stones; pass twice around it
+        // to remove one of the side to move
+        b'X' => {
+            let (n, last, last2, last3, ko) = (pos.n, pos.last, pos.last2, pos.last3, pos.ko);
+            pass_move(pos);
+            remove_stone(pos, pt);
+            pass_move(pos);
+            (pos.n, pos.last, pos.last2, pos.last3, pos.ko) = (n, last, last2, last3, ko);
+        }
+        b'x' => remove_stone(pos, pt),
+        _ => {}
+    }
+}
+
+/// Parse an SGF text and replay its main line (see [`load_positions`]).
+pub fn read_sgf(text: &str) -> Result<Vec<Position>> {
+    load_positions(&parse_sgf(text)?)
+}
+
+// =============================================================================
+// Writing
+// =============================================================================
+
+/// Format a board point as an SGF point ("" for pass).
+fn format_point(pt: Point, size: usize) -> String {
+    if pt == PASS_MOVE {
+        return String::new();
+    }
+    let col = (b'a' + (pt % W - 1) as u8) as char;
+    let row = (b'a' + (size - pt / W) as u8) as char;
+    format!("{col}{row}")
+}
+
+/// Black's stones of `pos` if `black`, else White's.
+fn stones_of(pos: &Position, black: bool) -> impl Iterator<Item = Point> + '_ {
+    let stone = if black == pos.is_black_to_play() {
+        STONE_BLACK
+    } else {
+        STONE_WHITE
+    };
+    (BOARD_IMIN..pos.imax()).filter(move |&pt| pos.color[pt] == stone)
+}
+
+/// The `AB` / `AW` / `AE` properties turning the stones of `before` into
+/// those of `after` (empty if they are the same).
+fn setup_properties(before: &Position, after: &Position) -> String {
+    let size = after.size;
+    let list = |points: Vec<Point>| -> String {
+        points
+            .iter()
+            .map(|&pt| format!("[{}]", format_point(pt, size)))
+            .collect()
+    };
+    let black: HashSet<Point> = stones_of(before, true).collect();
+    let white: HashSet<Point> = stones_of(before, false).collect();
+    let added_black: Vec<Point> = stones_of(after, true)
+        .filter(|p| !black.contains(p))
+        .collect();
+    let added_white: Vec<Point> = stones_of(after, false)
+        .filter(|p| !white.contains(p))
+        .collect();
+    let cleared: Vec<Point> = (BOARD_IMIN..after.imax())
+        .filter(|&pt| after.color[pt] == EMPTY && before.color[pt] != EMPTY)
+        .collect();
+
+    let mut props = String::new();
+    for (id, points) in [("AB", added_black), ("AW", added_white), ("AE", cleared)] {
+        if !points.is_empty() {
+            props.push_str(id);
+            props.push_str(&list(points));
+        }
+    }
+    props
+}
+
+/// Write a game as SGF.
+///
+/// `positions` is the sequence of positions of the game, starting with the
+/// initial one: its stones become `AB`/`AW` setup, and each following
+/// position contributes the move that led to it. A move played out of turn
+/// (see `play_move_for`) is written as a pass followed by the move. Stones
+/// that differ from what the move leaves (setup edits in the middle of a
+/// loaded game) follow in a setup node.
+pub fn write_sgf(positions: &[Position]) -> String {
+    write_sgf_with_comments(positions, &[])
+}
+
+/// Like [`write_sgf`], attaching `comments[i]` (if non-empty) to the node of
+/// the move leading to `positions[i + 1]`.
+pub fn write_sgf_with_comments(positions: &[Position], comments: &[String]) -> String {
+    write_sgf_game(positions, comments, None)
+}
+
+/// Like [`write_sgf_with_comments`], recording the outcome of a finished
+/// game (`RE`, such as `B+3.5` or `W+R`) if given.
+pub fn write_sgf_game(positions: &[Position], comments: &[String], result: Option<&str>) -> String {
+    let Some(first) = positions.first() else {
+        return "(;GM[1]FF[4])\n".to_string();
+    };
+    let size = first.size;
+
+    let mut sgf = format!(
+        "(;GM[1]FF[4]CA[UTF-8]AP[michi-rust:{}]SZ[{size}]KM[{}]",
+        env!("CARGO_PKG_VERSION"),
+        first.komi
+    );
+    if let Some(name) = first.rules.sgf_name() {
+        sgf.push_str(&format!("RU[{name}]"));
+    }
+    if first.handicap > 0 {
+        sgf.push_str(&format!("HA[{}]", first.handicap));
+    }
+    if let Some(result) = result {
+        sgf.push_str(&format!("RE[{result}]"));
+    }
+
+    // Setup stones of the initial position
+    sgf.push_str(&setup_properties(&Position::with_size(size), first));
+    if !first.is_black_to_play() {
+        sgf.push_str("PL[W]");
+    }
+
+    for (i, pair) in positions.windows(2).enumerate() {
+        let (prev, next) = (&pair[0], &pair[1]);
+        let color = |black: bool| if black { "B" } else { "W" };
+        let mut mover = prev.is_black_to_play();
+        if next.n >= prev.n + 2 {
+            // Implicit pass before a move out of turn
+            sgf.push_str(&format!(";{}[]", color(mover)));
+            mover = !mover;
+        }
+        sgf.push_str(&format!(
+            ";{}[{}]",
+            color(mover),
+            format_point(next.last, size)
+        ));
+        if let Some(comment) = comments.get(i).filter(|c| !c.is_empty()) {
+            let escaped = comment.replace('\\', "\\\\").replace(']', "\\]");
+            sgf.push_str(&format!("C[{escaped}]"));
+        }
+
+        // Stones edited after the move (setup nodes when loading) go in a
+        // setup node of their own
+        let mut replayed = prev.clone();
+        if next.n >= prev.n + 2 {
+            pass_move(&mut replayed);
+        }
+        if next.last == PASS_MOVE || play_move(&mut replayed, next.last).is_err() {
+            pass_move(&mut replayed);
+        }
+        let setup = setup_properties(&replayed, next);
+        let to_play = next.is_black_to_play();
+        if !setup.is_empty() || replayed.is_black_to_play() != to_play {
+            sgf.push(';');
+            sgf.push_str(&setup);
+            if replayed.is_black_to_play() != to_play {
+                sgf.push_str(if to_play { "PL[B]" } else { "PL[W]" });
+            }
+        }
+    }
+
+    sgf.push_str(")\n");
+    sgf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{STONE_BLACK, STONE_WHITE};
+    use crate::position::{parse_coord, str_coord};
+
+    #[test]
+    fn test_parse_tree_with_variations() {
+        let root = parse_sgf("(;GM[1]SZ[9] ;B[ee] (;W[cc];B[gg]) (;W[gc]))").unwrap();
+        assert_eq!(root.get("SZ"), Some("9"));
+        let b1 = &root.children[0];
+        assert_eq!(b1.get("B"), Some("ee"));
+        assert_eq!(b1.children.len(), 2);
+        assert_eq!(b1.children[1].get("W"), Some("gc"));
+
+        let main: Vec<_> = root
+            .main_line()
+            .filter_map(|n| n.get("B").or(n.get("W")))
+            .collect();
+        assert_eq!(main, ["ee", "cc", "gg"]);
+    }
+
+    #[test]
+    fn test_parse_escapes_and_old_ids() {
+        let root = parse_sgf("(;C[a \\] b]AddBlack[aa][bb])").unwrap();
+        assert_eq!(root.get("C"), Some("a ] b"));
+        assert_eq!(root.values("AB").len(), 2);
+
+        assert!(parse_sgf("(;B[aa]").is_err());
+        assert!(parse_sgf("B[aa]").is_err());
+    }
+
+    #[test]
+    fn test_sgf_points() {
+        assert_eq!(sgf_point("aa", 9).unwrap(), Some(parse_coord("A9")));
+        assert_eq!(sgf_point("ai", 9).unwrap(), Some(parse_coord("A1")));
+        assert_eq!(sgf_point("ss", 19).unwrap(), Some(parse_coord("T1")));
+        assert_eq!(sgf_point("", 19).unwrap(), None);
+        assert_eq!(sgf_point("tt", 19).unwrap(), None);
+        assert!(sgf_point("jj", 9).is_err());
+
+        let rect = sgf_points(&["aa:bb".to_string()], 9).unwrap();
+        assert_eq!(rect.len(), 4);
+        assert_eq!(format_point(parse_coord("D4"), 9), "df");
+    }
+
+    #[test]
+    fn test_load_setup_and_moves() {
+        let sgf = "(;SZ[13]KM[0.5]HA[2]RU[Japanese]AB[dj][jd]PL[W];W[dd];B[jj];W[])";
+        let positions = read_sgf(sgf).unwrap();
+        assert_eq!(positions.len(), 4);
+
+        let start = &positions[0];
+        assert_eq!(start.size, 13);
+        assert_eq!(start.komi, 0.5);
+        assert_eq!(start.handicap, 2);
+        assert_eq!(start.rules, Rules::japanese());
+        assert!(!start.is_black_to_play());
+        // White to play: Black's handicap stones are the opponent's
+        assert_eq!(start.color[parse_coord("D4")], STONE_WHITE);
+        assert_eq!(start.color[parse_coord("K10")], STONE_WHITE);
+
+        assert_eq!(str_coord(positions[1].last), "D10");
+        assert_eq!(str_coord(positions[2].last), "K4");
+        assert_eq!(positions[3].last, PASS_MOVE);
+        assert!(positions[3].is_black_to_play());
+    }
+
+    #[test]
+    fn test_load_errors() {
+        assert!(read_sgf("(;SZ[25])").is_err());
+        assert!(read_sgf("(;SZ[9];B[ee];W[ee])").is_err());
+        assert!(read_sgf("(;SZ[9];B[zz])").is_err());
+    }
+
+    #[test]
+    fn test_write_roundtrip() {
+        let sgf = "(;SZ[9]KM[6.5]AB[cc]AW[gg];B[ee];B[dd];W[];B[ef])";
+        let positions = read_sgf(sgf).unwrap();
+        let written = write_sgf(&positions);
+        assert!(written.contains("SZ[9]KM[6.5]RU[Chinese]"));
+        assert!(written.contains("AB[cc]AW[gg]"));
+        // The second Black move was out of turn: a White pass is inserted
+        assert!(written.contains(";B[ee];W[];B[dd];W[];B[ef])"));
+
+        let reread = read_sgf(&written).unwrap();
+        let (a, b) = (positions.last().unwrap(), reread.last().unwrap());
+        assert_eq!(a.color, b.color);
+        assert_eq!(a.n, b.n);
+        assert_eq!(a.hash, b.hash);
+    }
+
+    #[test]
+    fn test_write_comments_and_setup_edit() {
+        let positions = read_sgf("(;SZ[9];B[ee];W[dd]AE[ee]AB[ff])").unwrap();
+        assert_eq!(positions.len(), 3);
+        let last = &positions[2];
+        assert_eq!(last.color[parse_coord("E5")], EMPTY);
+        assert_eq!(last.color[parse_coord("F4")], STONE_BLACK);
+
+        let comments = vec!["wr 0.55".to_string(), "a]b".to_string()];
+        let written = write_sgf_with_comments(&positions[..3], &comments);
+        assert!(written.contains(";B[ee]C[wr 0.55];W[dd]C[a\\]b]"));
+
+        let written = write_sgf_game(&positions, &comments, Some("W+R"));
+        assert!(written.contains("RU[Chinese]RE[W+R];B[ee]"));
+    }
+
+    #[test]
+    fn test_setup_nodes_round_trip() {
+        let text = "(;SZ[9];B[ee];AB[cc][dd]AW[gg];W[ff];AE[ee]PL[W];W[ce])";
+        let positions = read_sgf(text).unwrap();
+        let written = write_sgf(&positions);
+        // The PL is written as the pass it stands for
+        assert!(written.contains(";B[ee];AB[dd][cc]AW[gg];W[ff];B[];AE[ee];W[ce])"));
+
+        let reread = read_sgf(&written).unwrap();
+        assert_eq!(reread.len(), positions.len());
+        for (a, b) in positions.iter().zip(&reread) {
+            assert_eq!(a.color, b.color);
+            assert_eq!(a.n, b.n);
+        }
+        // Black's D6 is not lost, and Black's E5 was removed (Black to move)
+        let last = positions.last().unwrap();
+        assert!(last.is_black_to_play());
+        assert_eq!(last.color[parse_coord("D6")], STONE_BLACK);
+        assert_eq!(last.color[parse_coord("E5")], EMPTY);
+    }
+}
@@ -0,0 +1,400 @@
+//! Time controls and per-move time budgets.
+//!
+//! [`TimeSettings`] describes the game's time system as set by GTP
+//! `time_settings` / `kgs-time_settings`; [`TimeManager`] tracks both players'
+//! clocks (updated by `time_left`, or locally after our own moves) and turns
+//! the time we have left into a [`Budget`] for the next move.
+//!
+//! A budget has a soft and a hard limit: search stops at the soft limit when
+//! the best move is clear, and may run on to the hard limit when it is not.
+
+use std::time::{Duration, Instant};
+
+use crate::constants::{MAX_MOVE_TIME, MIN_MOVES_LEFT, TIME_MARGIN, UNCLEAR_TIME_FACTOR};
+
+/// A time system. Times are in seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TimeSettings {
+    /// No time limit
+    #[default]
+    Unlimited,
+    /// Main time only (sudden death)
+    Absolute { main: f64 },
+    /// Japanese byo-yomi: `periods` periods of `period` seconds after main time
+    ByoYomi {
+        main: f64,
+        period: f64,
+        periods: u32,
+    },
+    /// Canadian byo-yomi: `stones` moves every `period` seconds after main time
+    Canadian { main: f64, period: f64, stones: u32 },
+}
+
+impl TimeSettings {
+    /// Settings from GTP `time_settings main byo_yomi_time byo_yomi_stones`.
+    ///
+    /// As in the GTP spec, no byo-yomi time means absolute time, and byo-yomi
+    /// time with no stones means no time limit.
+    pub fn from_gtp(main: f64, period: f64, stones: u32) -> Self {
+        if period <= 0.0 {
+            TimeSettings::Absolute { main }
+        } else if stones == 0 {
+            TimeSettings::Unlimited
+        } else {
+            TimeSettings::Canadian {
+                main,
+                period,
+                stones,
+            }
+        }
+    }
+
+    /// The clock at the start of the game.
+    fn start_clock(self) -> Clock {
+        match self {
+            TimeSettings::Unlimited => Clock::default(),
+            TimeSettings::Absolute { main } => Clock {
+                time: main,
+                stones: 0,
+            },
+            TimeSettings::ByoYomi {
+                main,
+                period,
+                periods,
+            } if main <= 0.0 => Clock {
+                time: period,
+                stones: periods,
+            },
+            TimeSettings::Canadian {
+                main,
+                period,
+                stones,
+            } if main <= 0.0 => Clock {
+                time: period,
+                stones,
+            },
+            TimeSettings::ByoYomi { main, .. } | TimeSettings::Canadian { main, .. } => Clock {
+                time: main,
+                stones: 0,
+            },
+        }
+    }
+}
+
+/// One player's clock, as GTP `time_left` reports it: the time left and,
+/// in byo-yomi, the stones (Canadian) or periods (Japanese) left. Zero
+/// stones means the player is still in main time.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Clock {
+    pub time: f64,
+    pub stones: u32,
+}
+
+/// Time to spend on a move: search until `soft`, or on to `hard` while the
+/// best move is unclear.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    pub soft: Duration,
+    pub hard: Duration,
+}
+
+impl Budget {
+    /// The deadline for a search started at `start`.
+    pub fn deadline(&self, start: Instant) -> Deadline {
+        Deadline {
+            soft: start + self.soft,
+            hard: start + self.hard,
+        }
+    }
+}
+
+/// Absolute end times of a move's search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    pub soft: Instant,
+    pub hard: Instant,
+}
+
+/// Both players' clocks under the current time settings.
+#[derive(Debug, Clone, Default)]
+pub struct TimeManager {
+    settings: TimeSettings,
+    /// Black's and White's clocks
+    clocks: [Clock; 2],
+}
+
+impl TimeManager {
+    /// The current time settings.
+    pub fn settings(&self) -> TimeSettings {
+        self.settings
+    }
+
+    /// Change the time settings, resetting both clocks.
+    pub fn set(&mut self, settings: TimeSettings) {
+        self.settings = settings;
+        self.clocks = [settings.start_clock(); 2];
+    }
+
+    /// Restart both clocks for a new game.
+    pub fn reset(&mut self) {
+        self.set(self.settings);
+    }
+
+    /// A player's clock.
+    pub fn clock(&self, black: bool) -> Clock {
+        self.clocks[usize::from(!black)]
+    }
+
+    /// Set a player's clock from GTP `time_left`.
+    pub fn time_left(&mut self, black: bool, time: f64, stones: u32) {
+        self.clocks[usize::from(!black)] = Clock { time, stones };
+    }
+
+    /// Charge `elapsed` seconds for a move to a player's clock.
+    ///
+    /// Keeps the clock roughly right between `time_left` updates, which
+    /// replace it when the controller sends them.
+    pub fn record(&mut self, black: bool, elapsed: f64) {
+        let settings = self.settings;
+        let clock = &mut self.clocks[usize::from(!black)];
+        match settings {
+            TimeSettings::Unlimited => {}
+            TimeSettings::Absolute { .. } => clock.time = (clock.time - elapsed).max(0.0),
+            TimeSettings::ByoYomi { period, .. } | TimeSettings::Canadian { period, .. }
+                if clock.stones == 0 =>
+            {
+                clock.time -= elapsed;
+                if clock.time <= 0.0 {
+                    // Main time ran out: byo-yomi starts
+                    let overrun = -clock.time;
+                    *clock = Self::byo_clock(settings);
+                    if overrun < period {
+                        clock.time -= overrun;
+                    }
+                }
+            }
+            TimeSettings::ByoYomi { period, .. } => {
+                if elapsed >= clock.time {
+                    clock.stones = clock.stones.saturating_sub(1).max(1);
+                }
+                clock.time = period;
+            }
+            TimeSettings::Canadian { period, stones, .. } => {
+                clock.time -= elapsed;
+                clock.stones -= 1;
+                if clock.stones == 0 {
+                    clock.time = period;
+                    clock.stones = stones;
+                }
+            }
+        }
+    }
+
+    /// A fresh byo-yomi period.
+    fn byo_clock(settings: TimeSettings) -> Clock {
+        match settings {
+            TimeSettings::ByoYomi {
+                period, periods, ..
+            } => Clock {
+                time: period,
+                stones: periods,
+            },
+            TimeSettings::Canadian { period, stones, .. } => Clock {
+                time: period,
+                stones,
+            },
+            _ => Clock::default(),
+        }
+    }
+
+    /// Time budget for the next move of a player, given the number of
+    /// empty points on the board, or `None` if there is no time limit.
+    pub fn budget(&self, black: bool, empty_points: usize) -> Option<Budget> {
+        let clock = self.clock(black);
+
+        // Time guaranteed for every move once main time is over
+        let per_move_byo = match self.settings {
+            TimeSettings::Unlimited => return None,
+            TimeSettings::Absolute { .. } => 0.0,
+            TimeSettings::ByoYomi { period, .. } => period,
+            TimeSettings::Canadian { period, stones, .. } => period / f64::from(stones.max(1)),
+        };
+
+        let (soft, hard) = if clock.stones > 0 {
+            // In byo-yomi: spend the time of this move's share of the period
+            let share = match self.settings {
+                TimeSettings::Canadian { .. } => clock.time / f64::from(clock.stones),
+                _ => clock.time,
+            };
+            (share, share)
+        } else {
+            // In main time: spread it over the moves likely left in the game
+            let moves_left = (empty_points / 3).max(MIN_MOVES_LEFT) as f64;
+            let soft = clock.time / moves_left + per_move_byo;
+            let hard = (soft * UNCLEAR_TIME_FACTOR).min(clock.time / 4.0 + per_move_byo);
+            (soft, hard.max(soft))
+        };
+
+        // `max` also turns NaN into the floor, and the cap keeps a huge
+        // clock from overflowing the duration
+        let floor = TIME_MARGIN / 10.0;
+        let secs = |t: f64| {
+            let t = (t - TIME_MARGIN).max(floor).min(MAX_MOVE_TIME);
+            Duration::try_from_secs_f64(t).unwrap_or(Duration::from_secs_f64(floor))
+        };
+        Some(Budget {
+            soft: secs(soft),
+            hard: secs(hard),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_gtp() {
+        assert_eq!(
+            TimeSettings::from_gtp(300.0, 0.0, 0),
+            TimeSettings::Absolute { main: 300.0 }
+        );
+        assert_eq!(
+            TimeSettings::from_gtp(0.0, 30.0, 0),
+            TimeSettings::Unlimited
+        );
+        assert_eq!(
+            TimeSettings::from_gtp(60.0, 30.0, 5),
+            TimeSettings::Canadian {
+                main: 60.0,
+                period: 30.0,
+                stones: 5
+            }
+        );
+    }
+
+    #[test]
+    fn test_unlimited_has_no_budget() {
+        let tm = TimeManager::default();
+        assert_eq!(tm.budget(true, 81), None);
+    }
+
+    #[test]
+    fn test_absolute_budget_spreads_main_time() {
+        let mut tm = TimeManager::default();
+        tm.set(TimeSettings::Absolute { main: 300.0 });
+        let budget = tm.budget(true, 81).unwrap();
+        assert!(budget.soft > Duration::from_secs(5) && budget.soft < Duration::from_secs(15));
+        assert!(budget.hard > budget.soft);
+        assert!(budget.hard <= Duration::from_secs(75));
+
+        // Less time left, smaller budget
+        tm.time_left(true, 30.0, 0);
+        assert!(tm.budget(true, 81).unwrap().soft < budget.soft);
+        // White's clock is separate
+        assert_eq!(tm.budget(false, 81), Some(budget));
+    }
+
+    #[test]
+    fn test_byo_yomi_budget() {
+        let mut tm = TimeManager::default();
+        tm.set(TimeSettings::ByoYomi {
+            main: 0.0,
+            period: 10.0,
+            periods: 3,
+        });
+        assert_eq!(
+            tm.clock(true),
+            Clock {
+                time: 10.0,
+                stones: 3
+            }
+        );
+        let budget = tm.budget(true, 81).unwrap();
+        assert_eq!(budget.soft, budget.hard);
+        assert!(budget.hard < Duration::from_secs(10));
+
+        // Canadian: the period is shared by the stones left
+        tm.set(TimeSettings::Canadian {
+            main: 0.0,
+            period: 60.0,
+            stones: 10,
+        });
+        tm.time_left(true, 30.0, 2);
+        let budget = tm.budget(true, 81).unwrap();
+        assert!(budget.hard < Duration::from_secs(15));
+        assert!(budget.hard > Duration::from_secs(14));
+    }
+
+    #[test]
+    fn test_budget_of_a_bad_clock() {
+        let max = Duration::from_secs_f64(MAX_MOVE_TIME);
+        let mut tm = TimeManager::default();
+        tm.set(TimeSettings::Absolute { main: 1e30 });
+        assert_eq!(tm.budget(true, 81).unwrap().hard, max);
+        tm.time_left(true, f64::INFINITY, 0);
+        assert_eq!(tm.budget(true, 81).unwrap().hard, max);
+        tm.time_left(true, f64::NAN, 0);
+        assert!(tm.budget(true, 81).unwrap().hard < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_record() {
+        let mut tm = TimeManager::default();
+        tm.set(TimeSettings::Canadian {
+            main: 10.0,
+            period: 60.0,
+            stones: 2,
+        });
+        tm.record(true, 4.0);
+        assert_eq!(
+            tm.clock(true),
+            Clock {
+                time: 6.0,
+                stones: 0
+            }
+        );
+        // Running out of main time enters byo-yomi
+        tm.record(true, 8.0);
+        assert_eq!(
+            tm.clock(true),
+            Clock {
+                time: 58.0,
+                stones: 2
+            }
+        );
+        tm.record(true, 8.0);
+        assert_eq!(
+            tm.clock(true),
+            Clock {
+                time: 50.0,
+                stones: 1
+            }
+        );
+        // A new period after the last stone of one
+        tm.record(true, 8.0);
+        assert_eq!(
+            tm.clock(true),
+            Clock {
+                time: 60.0,
+                stones: 2
+            }
+        );
+        assert_eq!(
+            tm.clock(false),
+            Clock {
+                time: 10.0,
+                stones: 0
+            }
+        );
+
+        tm.reset();
+        assert_eq!(
+            tm.clock(true),
+            Clock {
+                time: 10.0,
+                stones: 0
+            }
+        );
+    }
+}
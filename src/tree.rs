@@ -0,0 +1,372 @@
+//! Arena-allocated MCTS search tree.
+//!
+//! A [`TreeNode`] stores only the move leading to it and its statistics; the
+//! position at a node is rebuilt by replaying the moves from the root
+//! position, which only the [`Tree`] keeps.
+//!
+//! All nodes live in one vector, the children of a node in one contiguous
+//! block. The arena has a node cap: once it is reached, nodes are no longer
+//! expanded (the root excepted), so memory use is bounded whatever the
+//! simulation count.
+//!
+//! A tree also owns the random number generator of the searches run on it,
+//! so that seeding the tree makes them reproducible.
+
+use std::ops::Range;
+
+use fastrand::Rng;
+
+use crate::constants::{PASS_MOVE, PRIOR_EVEN, TREE_MEMORY_MB};
+use crate::position::{Point, Position, pass_move, play_move};
+
+/// Index of a node in its [`Tree`].
+pub type NodeId = u32;
+
+/// A node in the MCTS search tree.
+///
+/// Each node stores statistics for both regular visits (v, w) and AMAF visits (av, aw),
+/// as well as prior values (pv, pw) for initialization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeNode {
+    /// Move leading to this node
+    mv: u16,
+    /// Number of children (0 until expanded)
+    n_children: u16,
+    /// Index of the first child
+    first_child: NodeId,
+    /// Number of visits
+    pub v: u32,
+    /// Number of wins (winrate = w/v)
+    pub w: u32,
+    /// Prior visits (for initialization)
+    pub pv: u32,
+    /// Prior wins
+    pub pw: u32,
+    /// AMAF (All Moves As First) visits
+    pub av: u32,
+    /// AMAF wins
+    pub aw: u32,
+}
+
+impl TreeNode {
+    /// Create an unvisited node for the move `mv`, with the default even prior.
+    pub fn new(mv: Point) -> Self {
+        Self::with_prior(mv, PRIOR_EVEN)
+    }
+
+    /// Create an unvisited node for the move `mv` with an even prior of
+    /// `prior` visits (half of them wins).
+    pub fn with_prior(mv: Point, prior: u32) -> Self {
+        Self {
+            mv: mv as u16,
+            n_children: 0,
+            first_child: 0,
+            v: 0,
+            w: 0,
+            pv: prior,
+            pw: prior / 2,
+            av: 0,
+            aw: 0,
+        }
+    }
+
+    /// The move leading to this node.
+    #[inline]
+    pub fn mv(&self) -> Point {
+        self.mv as Point
+    }
+
+    /// Whether the node's children have been generated.
+    #[inline]
+    pub fn is_expanded(&self) -> bool {
+        self.n_children > 0
+    }
+
+    /// Calculate the winrate for this node.
+    #[inline]
+    pub fn winrate(&self) -> f64 {
+        if self.v > 0 {
+            self.w as f64 / self.v as f64
+        } else {
+            -0.1 // Indicate unvisited
+        }
+    }
+
+    /// The node with its statistics but without children.
+    fn detached(&self) -> Self {
+        Self {
+            n_children: 0,
+            first_child: 0,
+            ..*self
+        }
+    }
+}
+
+/// A search tree: the root position and the arena of nodes.
+#[derive(Debug, Clone)]
+pub struct Tree {
+    /// Position at the root
+    pos: Position,
+    /// All nodes; the root is `nodes[0]`
+    nodes: Vec<TreeNode>,
+    /// Most nodes the arena may hold
+    max_nodes: usize,
+    /// Random number generator of the searches on this tree
+    rng: Rng,
+}
+
+impl Tree {
+    /// Id of the root node.
+    pub const ROOT: NodeId = 0;
+
+    /// Create a tree with only a root at `pos`, capped at `TREE_MEMORY_MB`.
+    pub fn new(pos: &Position) -> Self {
+        Self::with_max_nodes(pos, Self::max_nodes_for_memory(TREE_MEMORY_MB << 20))
+    }
+
+    /// Create a tree with only a root at `pos`, holding at most `max_nodes`.
+    pub fn with_max_nodes(pos: &Position, max_nodes: usize) -> Self {
+        Self {
+            pos: pos.clone(),
+            nodes: vec![TreeNode::new(pos.last)],
+            max_nodes,
+            rng: Rng::new(),
+        }
+    }
+
+    /// Reseed the random number generator of the searches on this tree, so
+    /// that they can be replayed exactly.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = Rng::with_seed(seed);
+    }
+
+    /// The random number generator of the searches on this tree.
+    pub fn rng_mut(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    /// Number of nodes that fit in `bytes` of memory.
+    pub fn max_nodes_for_memory(bytes: usize) -> usize {
+        bytes / size_of::<TreeNode>()
+    }
+
+    /// The position at the root.
+    pub fn pos(&self) -> &Position {
+        &self.pos
+    }
+
+    /// The root node.
+    pub fn root(&self) -> &TreeNode {
+        &self.nodes[Self::ROOT as usize]
+    }
+
+    /// A node by id.
+    #[inline]
+    pub fn node(&self, id: NodeId) -> &TreeNode {
+        &self.nodes[id as usize]
+    }
+
+    /// A node by id, mutably.
+    #[inline]
+    pub fn node_mut(&mut self, id: NodeId) -> &mut TreeNode {
+        &mut self.nodes[id as usize]
+    }
+
+    /// Ids of the children of a node.
+    #[inline]
+    pub fn child_ids(&self, id: NodeId) -> Range<NodeId> {
+        let node = self.node(id);
+        node.first_child..node.first_child + NodeId::from(node.n_children)
+    }
+
+    /// The children of a node.
+    #[inline]
+    pub fn children(&self, id: NodeId) -> &[TreeNode] {
+        let ids = self.child_ids(id);
+        &self.nodes[ids.start as usize..ids.end as usize]
+    }
+
+    /// The children of a node, mutably.
+    #[inline]
+    pub fn children_mut(&mut self, id: NodeId) -> &mut [TreeNode] {
+        let ids = self.child_ids(id);
+        &mut self.nodes[ids.start as usize..ids.end as usize]
+    }
+
+    /// Number of nodes allocated.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Memory used by the nodes, in bytes.
+    pub fn memory(&self) -> usize {
+        self.nodes.len() * size_of::<TreeNode>()
+    }
+
+    /// Most nodes the arena may hold.
+    pub fn max_nodes(&self) -> usize {
+        self.max_nodes
+    }
+
+    /// Change the node cap. Nodes already allocated are kept.
+    pub fn set_max_nodes(&mut self, max_nodes: usize) {
+        self.max_nodes = max_nodes;
+    }
+
+    /// Whether `n` more nodes fit under the cap.
+    pub fn has_room(&self, n: usize) -> bool {
+        self.nodes.len() + n <= self.max_nodes
+    }
+
+    /// Give an unexpanded node its children, allocated as one block.
+    pub fn add_children(&mut self, id: NodeId, children: &[TreeNode]) {
+        debug_assert!(!self.node(id).is_expanded());
+        let first = self.nodes.len() as NodeId;
+        self.nodes.extend(children.iter().map(TreeNode::detached));
+        let node = self.node_mut(id);
+        node.first_child = first;
+        node.n_children = children.len() as u16;
+    }
+
+    /// Keep only the children of a node that `keep` accepts.
+    ///
+    /// The kept children (with their subtrees) move to the front of the
+    /// block; the slots of the others are not reused until the tree is
+    /// compacted by [`Tree::into_child`].
+    pub fn retain_children(&mut self, id: NodeId, mut keep: impl FnMut(&TreeNode) -> bool) {
+        let ids = self.child_ids(id);
+        let ids_start = ids.start;
+        let mut kept = ids.start;
+        for child in ids {
+            if keep(self.node(child)) {
+                self.nodes[kept as usize] = *self.node(child);
+                kept += 1;
+            }
+        }
+        self.node_mut(id).n_children = (kept - ids_start) as u16;
+    }
+
+    /// Play a node's move on `pos`, which must be the position at its parent.
+    pub fn replay(pos: &mut Position, mv: Point) {
+        if mv == PASS_MOVE {
+            pass_move(pos);
+        } else {
+            play_move(pos, mv).expect("moves in the tree are legal");
+        }
+    }
+
+    /// Whether the tree is rooted at `pos` (same stones, side to move and ko).
+    pub fn is_at(&self, pos: &Position) -> bool {
+        same_position(&self.pos, pos)
+    }
+
+    /// Re-root the tree at the child whose position is `pos`, keeping that
+    /// child's statistics and subtree, so that search can continue from there.
+    ///
+    /// The subtree is copied into a fresh arena, which frees the rest of the
+    /// tree. Returns `None` if no child is at `pos`.
+    pub fn into_child(self, pos: &Position) -> Option<Tree> {
+        let child = self.child_ids(Self::ROOT).find(|&id| {
+            let mut child_pos = self.pos.clone();
+            Self::replay(&mut child_pos, self.node(id).mv());
+            same_position(&child_pos, pos)
+        })?;
+
+        let mut tree = Self {
+            pos: pos.clone(),
+            nodes: vec![self.node(child).detached()],
+            max_nodes: self.max_nodes,
+            rng: self.rng.clone(),
+        };
+        tree.copy_subtree(Self::ROOT, &self, child);
+        Some(tree)
+    }
+
+    /// Copy the descendants of `src_id` in `src` below the unexpanded node
+    /// `id`, as far as the node cap allows.
+    pub fn copy_subtree(&mut self, id: NodeId, src: &Tree, src_id: NodeId) {
+        let mut queue = vec![(src_id, id)];
+        while let Some((from, to)) = queue.pop() {
+            let children = src.children(from);
+            if children.is_empty() || !self.has_room(children.len()) {
+                continue;
+            }
+            self.add_children(to, children);
+            queue.extend(src.child_ids(from).zip(self.child_ids(to)));
+        }
+    }
+}
+
+/// Whether two positions have the same stones, side to move and ko.
+fn same_position(a: &Position, b: &Position) -> bool {
+    a.n == b.n && a.ko == b.ko && a.hash == b.hash && a.color == b.color
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::parse_coord;
+
+    fn tree_with_children(moves: &[&str]) -> Tree {
+        let mut tree = Tree::new(&Position::new());
+        let children: Vec<TreeNode> = moves
+            .iter()
+            .map(|m| TreeNode::new(parse_coord(m)))
+            .collect();
+        tree.add_children(Tree::ROOT, &children);
+        tree
+    }
+
+    #[test]
+    fn test_node_is_compact() {
+        assert_eq!(size_of::<TreeNode>(), 32);
+    }
+
+    #[test]
+    fn test_add_and_retain_children() {
+        let mut tree = tree_with_children(&["C3", "E5", "G7"]);
+        assert_eq!(tree.node_count(), 4);
+        assert_eq!(tree.child_ids(Tree::ROOT), 1..4);
+        assert_eq!(tree.children(Tree::ROOT)[1].mv(), parse_coord("E5"));
+
+        tree.retain_children(Tree::ROOT, |c| c.mv() != parse_coord("E5"));
+        let moves: Vec<Point> = tree.children(Tree::ROOT).iter().map(|c| c.mv()).collect();
+        assert_eq!(moves, vec![parse_coord("C3"), parse_coord("G7")]);
+    }
+
+    #[test]
+    fn test_into_child_compacts() {
+        let mut tree = tree_with_children(&["C3", "E5", "G7"]);
+        let e5 = 2;
+        tree.node_mut(e5).v = 5;
+        tree.add_children(
+            e5,
+            &[TreeNode::new(parse_coord("D4")), TreeNode::new(PASS_MOVE)],
+        );
+        tree.add_children(3, &[TreeNode::new(parse_coord("F6"))]);
+
+        let mut pos = Position::new();
+        play_move(&mut pos, parse_coord("E5")).unwrap();
+        let child = tree.into_child(&pos).unwrap();
+        assert!(child.is_at(&pos));
+        assert_eq!(child.root().v, 5);
+        assert_eq!(child.node_count(), 3, "only the E5 subtree is kept");
+        assert_eq!(child.children(Tree::ROOT)[0].mv(), parse_coord("D4"));
+        assert_eq!(child.children(Tree::ROOT)[1].mv(), PASS_MOVE);
+    }
+
+    #[test]
+    fn test_node_cap() {
+        let mut tree = Tree::with_max_nodes(&Position::new(), 3);
+        assert!(tree.has_room(2));
+        assert!(!tree.has_room(3));
+        tree.add_children(Tree::ROOT, &[TreeNode::new(PASS_MOVE)]);
+
+        // Copying stops at the cap
+        let src = tree_with_children(&["C3", "E5", "G7"]);
+        let mut small = Tree::with_max_nodes(&Position::new(), 3);
+        small.copy_subtree(Tree::ROOT, &src, Tree::ROOT);
+        assert!(!small.root().is_expanded());
+        assert_eq!(Tree::max_nodes_for_memory(1 << 20), (1 << 20) / 32);
+    }
+}
@@ -4,15 +4,17 @@
 //! - fix_atari.tst - Tests for fix_atari and ladder detection
 //! - large_pat.tst - Tests for large pattern matching
 //!
-//! Test data files are located in tests/data/
+//! Test data files are located in tests/data/. Besides the ported cases,
+//! the `.tst` files are also run as they are through [`regress`].
 
 use std::path::Path;
 
-use michi_rust::constants::N;
+use michi_rust::gtp::GtpEngine;
 use michi_rust::patterns::{load_large_patterns_from, matching_pattern_ids};
 use michi_rust::position::{
     Position, fix_atari, fix_atari_ext, parse_coord, pass_move, play_move, str_coord,
 };
+use michi_rust::regress::{self, Outcome};
 
 // =============================================================================
 // Helper functions
@@ -23,7 +25,12 @@ use michi_rust::position::{
 /// Moves are played alternately: Black, White, Black, White, ...
 /// Use "pass" or "PASS" to skip a turn.
 fn setpos(moves: &[&str]) -> Position {
-    let mut pos = Position::new();
+    setpos_sized(9, moves)
+}
+
+/// Like `setpos`, on a board of the given size.
+fn setpos_sized(size: usize, moves: &[&str]) -> Position {
+    let mut pos = Position::with_size(size);
     for mv in moves {
         let pt = parse_coord(mv);
         if pt == 0 {
@@ -110,12 +117,13 @@ fn test_fix_atari_30_escape_blocked() {
     let b1 = parse_coord("B1");
     let moves = fix_atari(&pos, b1, false);
 
-    // The group at B1 is in atari
-    // This test verifies the function returns atari status
-    // E5 doesn't affect B1's status
+    // White's B1 is in atari and it is now White's turn: extending to A1
+    // leaves a single liberty and there is nothing to counter-capture,
+    // so the expected answer is "in atari, no moves".
     assert!(
-        !moves.is_empty() || true, // Just verify no panic
-        "Test 30: fix_atari should handle this position"
+        moves.is_empty(),
+        "Test 30: Expected no moves, got: [{}]",
+        format_moves(&moves)
     );
 }
 
@@ -314,15 +322,9 @@ fn load_test_patterns() -> bool {
         let prob_path = Path::new("tests/data/patterns.prob");
         let spat_path = Path::new("tests/data/patterns.spat");
 
-        match load_large_patterns_from(prob_path, spat_path) {
-            Ok(_) => true,
-            Err(_) => false,
-        }
+        load_large_patterns_from(prob_path, spat_path).is_ok()
     } else {
-        match load_large_patterns_from(prob_path, spat_path) {
-            Ok(_) => true,
-            Err(_) => false,
-        }
+        load_large_patterns_from(prob_path, spat_path).is_ok()
     }
 }
 
@@ -418,11 +420,6 @@ fn test_large_pat_30_rotation_90() {
 #[test]
 fn test_large_pat_40_rotation_180() {
     // This test uses coordinates that are only valid on 13x13
-    if N != 13 {
-        eprintln!("Skipping test_large_pat_40: Requires 13x13 board");
-        return;
-    }
-
     if !load_test_patterns() {
         eprintln!("Skipping test_large_pat_40: Pattern files not found");
         return;
@@ -433,7 +430,10 @@ fn test_large_pat_40_rotation_180() {
     // 40 debug match_pat E12
     // Expected: [923280]
 
-    let pos = setpos(&["F13", "F12", "F11", "G12", "E11", "D11", "E10", "D13"]);
+    let pos = setpos_sized(
+        13,
+        &["F13", "F12", "F11", "G12", "E11", "D11", "E10", "D13"],
+    );
     let e12 = parse_coord("E12");
 
     let ids = matching_pattern_ids(&pos, e12);
@@ -452,11 +452,6 @@ fn test_large_pat_40_rotation_180() {
 #[test]
 fn test_large_pat_50_rotation_270() {
     // This test uses coordinates that are only valid on 13x13
-    if N != 13 {
-        eprintln!("Skipping test_large_pat_50: Requires 13x13 board");
-        return;
-    }
-
     if !load_test_patterns() {
         eprintln!("Skipping test_large_pat_50: Pattern files not found");
         return;
@@ -467,7 +462,7 @@ fn test_large_pat_50_rotation_270() {
     // 50 debug match_pat M9
     // Expected: [923280]
 
-    let pos = setpos(&["N8", "M8", "L8", "M7", "L9", "L10", "K9", "N10"]);
+    let pos = setpos_sized(13, &["N8", "M8", "L8", "M7", "L9", "L10", "K9", "N10"]);
     let m9 = parse_coord("M9");
 
     let ids = matching_pattern_ids(&pos, m9);
@@ -491,17 +486,12 @@ fn test_large_pat_60_vertical_flip() {
     // Expected: [923280]
 
     // This test uses K2 which is only valid on 13x13 (K is column 10)
-    if N < 13 {
-        eprintln!("Skipping test_large_pat_60: K2 requires 13x13 board");
-        return;
-    }
-
     if !load_test_patterns() {
         eprintln!("Skipping test_large_pat_60: Pattern files not found");
         return;
     }
 
-    let pos = setpos(&["J1", "J2", "J3", "K2", "H3", "G3", "H4", "G1"]);
+    let pos = setpos_sized(13, &["J1", "J2", "J3", "K2", "H3", "G3", "H4", "G1"]);
     let h2 = parse_coord("H2");
 
     let ids = matching_pattern_ids(&pos, h2);
@@ -545,42 +535,144 @@ fn test_large_pat_70_corner() {
 }
 
 // =============================================================================
-// Summary test that runs all fix_atari tests in sequence (like the .tst file)
+// The same suites on a 19x19 board
+//
+// Positions near the bottom and left edges are unchanged; positions that rely
+// on the top or right edge of the 9x9/13x13 board are shifted to the 19x19 edge.
+// Ladder tests are not repeated since ladders run further on the larger board.
 // =============================================================================
 
 #[test]
-fn test_fix_atari_suite() {
-    println!("Running fix_atari test suite...");
-    println!("  Test 10: Basic escape - OK");
-    println!("  Test 20: Corner escape - OK");
-    println!("  Test 110: Counter-capture - OK");
-    println!("  Test 210: Simple ladder - OK");
-    println!("  Test 220: Broken ladder - OK");
-    println!("  Test 230: Ladder blocked - OK");
-    println!("  Test 240: Two-lib attack - OK");
-    println!("  Test 250: Two-lib edge - OK");
-    println!("  Test 260: In atari - OK");
-    println!("All fix_atari tests passed!");
+fn test_fix_atari_19x19() {
+    // (setpos, group to check, expected move or None for "in atari, no moves")
+    let cases: &[(&[&str], &str, Option<&str>)] = &[
+        (
+            &["C18", "C19", "E19", "B18", "F19", "D18"],
+            "C18",
+            Some("C17"),
+        ),
+        (&["C1", "G7", "B2", "B1"], "B1", Some("A1")),
+        (&["A1", "E5", "B2", "A2"], "A1", Some("A3")),
+        (
+            &["D3", "F3", "E3", "G3", "F2", "E2", "G2", "H2", "D2"],
+            "E2",
+            None,
+        ),
+    ];
+
+    for (moves, group, expected) in cases {
+        let pos = setpos_sized(19, moves);
+        let result = fix_atari(&pos, parse_coord(group), false);
+        match expected {
+            Some(mv) => assert!(
+                result.contains(&parse_coord(mv)),
+                "19x19 fix_atari {group}: expected {mv}, got: [{}]",
+                format_moves(&result)
+            ),
+            None => assert!(
+                result.is_empty(),
+                "19x19 fix_atari {group}: expected no moves, got: [{}]",
+                format_moves(&result)
+            ),
+        }
+    }
 }
 
 #[test]
-fn test_large_pattern_suite() {
+fn test_large_pat_19x19() {
     if !load_test_patterns() {
-        println!("Large pattern tests skipped: Pattern files not found");
+        eprintln!("Skipping test_large_pat_19x19: Pattern files not found");
         return;
     }
 
-    println!("Running large pattern test suite...");
-    println!("  Test 10: Size 5 center pattern - OK");
-    println!("  Test 20: Size 4 side pattern - OK");
-    println!("  Test 30: 90° rotation - OK");
-    if N >= 13 {
-        println!("  Test 40: 180° rotation - OK");
-        println!("  Test 50: 270° rotation - OK");
-        println!("  Test 60: Vertical flip - OK");
-    } else {
-        println!("  Test 40-60: Skipped (requires 13x13)");
+    // (setpos, point to match, expected pattern id)
+    let cases: &[(&[&str], &str, u32)] = &[
+        (
+            &[
+                "D6", "E6", "D5", "E5", "D4", "E3", "F6", "pass", "F5", "pass", "F4", "pass",
+            ],
+            "E4",
+            410926,
+        ),
+        (
+            &["D1", "D2", "D3", "C2", "E3", "F3", "E4", "F1"],
+            "E2",
+            923280,
+        ),
+        (
+            &["A5", "B5", "C5", "B6", "C4", "C3", "D4", "A3"],
+            "B4",
+            923280,
+        ),
+        (
+            &["F19", "F18", "F17", "G18", "E17", "D17", "E16", "D19"],
+            "E18",
+            923280,
+        ),
+        (
+            &["T8", "S8", "R8", "S7", "R9", "R10", "Q9", "T10"],
+            "S9",
+            923280,
+        ),
+        (
+            &["J1", "J2", "J3", "K2", "H3", "G3", "H4", "G1"],
+            "H2",
+            923280,
+        ),
+        (
+            &[
+                "B2", "A2", "C3", "B3", "D3", "C2", "D2", "C4", "E2", "D4", "F2", "E4", "F3", "F4",
+                "F1", "E3", "G2", "G3",
+            ],
+            "B1",
+            125951,
+        ),
+    ];
+
+    for (moves, pt, expected) in cases {
+        let pos = setpos_sized(19, moves);
+        let ids = matching_pattern_ids(&pos, parse_coord(pt));
+        assert!(
+            ids.contains(expected),
+            "19x19 match_pat {pt}: expected pattern ID {expected}, got: {ids:?}"
+        );
+    }
+}
+
+// =============================================================================
+// The .tst files themselves, run through the GTP engine
+// =============================================================================
+
+/// Run a michi-c suite on a fresh engine at michi-c's 13x13 board size and
+/// check that every case passes.
+fn run_suite(path: &str) {
+    let mut engine = GtpEngine::with_simulations(10);
+    assert!(engine.execute_line("boardsize 13").0);
+    let results = regress::run_file(&mut engine, Path::new(path)).unwrap();
+    assert!(!results.is_empty(), "{path}: no test cases");
+    for case in &results {
+        assert_eq!(
+            case.outcome,
+            Outcome::Pass,
+            "{path}:{}: {} got [{}], expected {}",
+            case.line,
+            case.command,
+            case.response,
+            case.expected
+        );
+    }
+}
+
+#[test]
+fn test_fix_atari_suite() {
+    run_suite("tests/data/fix_atari.tst");
+}
+
+#[test]
+fn test_large_pattern_suite() {
+    if !load_test_patterns() {
+        eprintln!("Skipping test_large_pattern_suite: Pattern files not found");
+        return;
     }
-    println!("  Test 70: Corner pattern - OK");
-    println!("All large pattern tests passed!");
+    run_suite("tests/data/large_pat.tst");
 }
@@ -6,6 +6,7 @@
 //! - large_pat.tst tests require large pattern matching
 
 use anyhow::Result;
+use michi_rust::config::SearchConfig;
 use michi_rust::position::{
     Position, fix_atari, fix_atari_ext, is_eye, is_eyeish, parse_coord, pass_move, play_move,
     str_coord,
@@ -44,7 +45,8 @@ fn setpos_alt(moves: &[&str]) -> Result<Position> {
 // Coordinate parsing and string conversion tests
 // =============================================================================
 
-use michi_rust::constants::N;
+// Positions in these tests use the default board size.
+use michi_rust::constants::{DEFAULT_N as N, W};
 
 /// Get the far corner coordinate string based on board size.
 /// Returns "J9" for 9x9 and "N13" for 13x13.
@@ -160,10 +162,9 @@ fn test_empty_position() {
     assert_eq!(pos.cap_x, 0, "Opponent captures should be 0");
 
     // Check that all board points are empty
-    let w = N + 1; // Width including padding
     for row in 1..=N {
         for col in 1..=N {
-            let pt = row * w + col;
+            let pt = row * W + col;
             assert_eq!(
                 pos.color[pt], b'.',
                 "Point at row {} col {} should be empty",
@@ -491,53 +492,472 @@ fn test_is_eye_true_eye() -> Result<()> {
 
 #[test]
 fn test_tree_node_creation() {
-    use michi_rust::mcts::TreeNode;
+    use michi_rust::mcts::Tree;
 
     let pos = Position::new();
-    let node = TreeNode::new(&pos);
+    let tree = Tree::new(&pos);
+    let node = tree.root();
 
     assert_eq!(node.v, 0, "Initial visits should be 0");
     assert_eq!(node.w, 0, "Initial wins should be 0");
-    assert!(node.children.is_empty(), "New node should have no children");
+    assert!(!node.is_expanded(), "New node should have no children");
+    assert_eq!(tree.node_count(), 1);
 }
 
 #[test]
 fn test_tree_expand() {
-    use michi_rust::mcts::{TreeNode, expand};
+    use michi_rust::mcts::{Tree, expand};
 
     let pos = Position::new();
-    let mut node = TreeNode::new(&pos);
+    let mut tree = Tree::new(&pos);
 
-    assert!(node.children.is_empty());
-    expand(&mut node);
+    assert!(!tree.root().is_expanded());
+    expand(&mut tree, Tree::ROOT, &pos, &SearchConfig::default());
     assert!(
-        !node.children.is_empty(),
+        tree.root().is_expanded(),
         "Expanded node should have children"
     );
 
     // On an empty board, there should be many legal moves
     // 9x9 has 81 points, 13x13 has 169 points
     let min_moves = if N == 9 { 50 } else { 100 };
+    let children = tree.children(Tree::ROOT).len();
     assert!(
-        node.children.len() > min_moves,
+        children > min_moves,
         "Should have many legal moves, got {}",
-        node.children.len()
+        children
     );
 }
 
+#[test]
+fn test_expand_uses_config_priors() {
+    use michi_rust::mcts::{Tree, expand};
+
+    let pos = Position::new();
+    let config = SearchConfig {
+        prior_even: 40,
+        prior_emptyarea: 0,
+        ..SearchConfig::default()
+    };
+    let mut tree = Tree::new(&pos);
+    expand(&mut tree, Tree::ROOT, &pos, &config);
+
+    // No stones nearby and no empty-area penalty: only the even prior is left
+    let child = tree.children(Tree::ROOT)[0];
+    assert_eq!((child.pv, child.pw), (40, 20));
+}
+
+#[test]
+fn test_expand_respects_node_cap() {
+    use michi_rust::mcts::{Tree, expand, tree_search};
+
+    let mut pos = Position::new();
+    let mut tree = Tree::with_max_nodes(&pos, 100);
+
+    // The root is always expanded, other nodes only while there is room
+    expand(&mut tree, Tree::ROOT, &pos, &SearchConfig::default());
+    assert_eq!(tree.node_count(), 82);
+    let child = tree.child_ids(Tree::ROOT).start;
+    play_move(&mut pos, tree.node(child).mv()).unwrap();
+    expand(&mut tree, child, &pos, &SearchConfig::default());
+    assert!(!tree.node(child).is_expanded());
+
+    // Search still works on a full tree, and stays under the cap
+    tree_search(&mut tree, 200, &SearchConfig::default());
+    assert!(tree.node_count() <= 100);
+    assert!(tree.root().v > 0);
+}
+
+#[test]
+fn test_expand_root_with_filter() {
+    use michi_rust::constants::PASS_MOVE;
+    use michi_rust::mcts::{Tree, expand_root_with};
+
+    let pos = Position::new();
+    let e5 = parse_coord("E5");
+
+    let mut tree = Tree::new(&pos);
+    expand_root_with(&mut tree, &SearchConfig::default(), |p| p.last != e5);
+    assert!(tree.root().is_expanded());
+    assert!(tree.children(Tree::ROOT).iter().all(|c| c.mv() != e5));
+
+    // Rejecting every move leaves only a pass
+    let mut tree = Tree::new(&pos);
+    expand_root_with(&mut tree, &SearchConfig::default(), |_| false);
+    assert_eq!(tree.children(Tree::ROOT).len(), 1);
+    assert_eq!(tree.children(Tree::ROOT)[0].mv(), PASS_MOVE);
+}
+
+#[test]
+fn test_tree_into_child() {
+    use michi_rust::mcts::{Tree, tree_search};
+
+    let mut pos = Position::new();
+    let mut tree = Tree::new(&pos);
+    tree_search(&mut tree, 200, &SearchConfig::default());
+    assert!(tree.is_at(&pos));
+
+    let best = tree
+        .children(Tree::ROOT)
+        .iter()
+        .max_by_key(|c| c.v)
+        .unwrap();
+    let (pt, visits) = (best.mv(), best.v);
+    assert!(visits > 0);
+
+    let size = tree.node_count();
+    play_move(&mut pos, pt).unwrap();
+    let child = tree.into_child(&pos).expect("child for the played move");
+    assert!(child.is_at(&pos));
+    assert_eq!(child.root().v, visits, "statistics are kept");
+    assert!(child.node_count() < size, "the rest of the tree is freed");
+
+    // A position that is not one of the children
+    pass_move(&mut pos);
+    assert!(child.into_child(&pos).is_none());
+}
+
 #[test]
 fn test_tree_search_basic() {
     use michi_rust::constants::BOARDSIZE;
-    use michi_rust::mcts::{TreeNode, tree_search};
+    use michi_rust::mcts::{StopReason, Tree, tree_search};
 
     let pos = Position::new();
-    let mut root = TreeNode::new(&pos);
+    let mut tree = Tree::new(&pos);
 
     // Run a small number of simulations
-    let best_move = tree_search(&mut root, 10);
+    let result = tree_search(&mut tree, 10, &SearchConfig::default());
 
     // Should return a valid move or pass
-    assert!(best_move < BOARDSIZE, "Move should be a valid board index");
+    assert!(
+        result.best_move < BOARDSIZE,
+        "Move should be a valid board index"
+    );
+    assert!((0.0..=1.0).contains(&result.winrate));
+
+    // The result describes the tree: every simulation went through the root
+    assert_eq!(result.sims, tree.root().v as usize);
+    assert_eq!(result.stop == StopReason::Completed, result.sims == 10);
+    assert_eq!(result.candidates.len(), tree.children(Tree::ROOT).len());
+    assert!(
+        result
+            .candidates
+            .windows(2)
+            .all(|w| w[0].visits >= w[1].visits),
+        "candidates are sorted by visits"
+    );
+    let best = &result.candidates[0];
+    assert_eq!(best.mv, result.best_move);
+    assert_eq!(best.winrate(), Some(result.winrate));
+    assert_eq!(result.pv()[0], result.best_move);
+    assert!(best.prior_visits > 0);
+}
+
+#[test]
+fn test_tree_search_parallel_merges_stats() {
+    use michi_rust::constants::BOARDSIZE;
+    use michi_rust::mcts::{NullObserver, SearchLimits, Tree, tree_search_parallel};
+
+    let pos = Position::new();
+    let mut tree = Tree::new(&pos);
+    let mut owner_map = vec![0i32; BOARDSIZE];
+    let result = tree_search_parallel(
+        &mut tree,
+        400,
+        4,
+        &SearchConfig::default(),
+        &mut owner_map,
+        &SearchLimits::default(),
+        &mut NullObserver,
+    );
+    let best_move = result.best_move;
+    assert!(best_move < BOARDSIZE);
+
+    // Every simulation of every thread is counted at the root, and each
+    // one passed through exactly one root child
+    let root_visits = tree.root().v;
+    assert!(root_visits > 0 && root_visits <= 400);
+    assert_eq!(result.sims, root_visits as usize);
+    let children = tree.children(Tree::ROOT);
+    let child_visits: u32 = children.iter().map(|c| c.v).sum();
+    assert_eq!(child_visits, root_visits);
+    // The best move is a most visited child (several may tie)
+    let most_visits = children.iter().map(|c| c.v).max().unwrap();
+    let best = children.iter().find(|c| c.mv() == best_move).unwrap();
+    assert_eq!(best.v, most_visits);
+
+    // Searching again adds to the merged tree
+    tree_search_parallel(
+        &mut tree,
+        200,
+        2,
+        &SearchConfig::default(),
+        &mut owner_map,
+        &SearchLimits::default(),
+        &mut NullObserver,
+    );
+    assert!(tree.root().v > root_visits);
+}
+
+#[test]
+fn test_search_observer_events() {
+    use michi_rust::constants::{BOARDSIZE, REPORT_PERIOD};
+    use michi_rust::mcts::{
+        SearchLimits, SearchObserver, SearchResult, Tree, tree_search_observed,
+        tree_search_parallel,
+    };
+
+    #[derive(Default)]
+    struct Recorder {
+        sims: Vec<usize>,
+        reports: Vec<usize>,
+        finished: Option<(usize, u32)>,
+    }
+
+    impl SearchObserver for Recorder {
+        fn on_sim(&mut self, tree: &Tree, sims: usize) {
+            assert_eq!(tree.root().v as usize, sims);
+            self.sims.push(sims);
+        }
+
+        fn on_report(&mut self, _tree: &Tree, sims: usize) {
+            self.reports.push(sims);
+        }
+
+        fn on_finish(&mut self, tree: &Tree, result: &SearchResult) {
+            assert!(self.finished.is_none(), "finished only once");
+            self.finished = Some((result.sims, tree.root().v));
+        }
+    }
+
+    // No fast play, so that every simulation is run
+    let config = SearchConfig {
+        fastplay5_thres: 1.0,
+        fastplay20_thres: 1.0,
+        ..SearchConfig::default()
+    };
+    let mut tree = Tree::new(&Position::new());
+    let mut owner_map = vec![0i32; BOARDSIZE];
+    let mut recorder = Recorder::default();
+    let sims = REPORT_PERIOD + 5;
+    let result = tree_search_observed(
+        &mut tree,
+        sims,
+        &config,
+        Some(&mut owner_map),
+        &SearchLimits::default(),
+        &mut recorder,
+    );
+
+    assert_eq!(result.sims, sims);
+    assert_eq!(recorder.sims, (1..=sims).collect::<Vec<_>>());
+    assert_eq!(recorder.reports, vec![REPORT_PERIOD]);
+    assert_eq!(recorder.finished, Some((sims, sims as u32)));
+    assert!(owner_map.iter().any(|&o| o != 0), "ownership was tracked");
+
+    // With two threads, progress follows the first thread's share of the
+    // simulations on its own tree, and the end the merged tree
+    let mut tree = Tree::new(&Position::new());
+    let mut recorder = Recorder::default();
+    let result = tree_search_parallel(
+        &mut tree,
+        2 * sims,
+        2,
+        &config,
+        &mut owner_map,
+        &SearchLimits::default(),
+        &mut recorder,
+    );
+    assert_eq!(result.sims, 2 * sims);
+    assert_eq!(recorder.sims, (1..=sims).collect::<Vec<_>>());
+    assert_eq!(recorder.reports, vec![REPORT_PERIOD]);
+    assert_eq!(recorder.finished, Some((2 * sims, 2 * sims as u32)));
+}
+
+#[test]
+fn test_search_stops_at_deadline() {
+    use michi_rust::constants::BOARDSIZE;
+    use michi_rust::mcts::{
+        NullObserver, SearchLimits, StopReason, Tree, out_of_time, tree_search_parallel,
+    };
+    use michi_rust::timecontrol::Budget;
+    use std::time::{Duration, Instant};
+
+    let pos = Position::new();
+    let mut tree = Tree::new(&pos);
+    let mut owner_map = vec![0i32; BOARDSIZE];
+    let budget = Budget {
+        soft: Duration::from_millis(100),
+        hard: Duration::from_millis(300),
+    };
+    let start = Instant::now();
+    let limits = SearchLimits {
+        deadline: Some(budget.deadline(start)),
+        ..SearchLimits::default()
+    };
+    for threads in [1, 2] {
+        let result = tree_search_parallel(
+            &mut tree,
+            10_000_000,
+            threads,
+            &SearchConfig::default(),
+            &mut owner_map,
+            &limits,
+            &mut NullObserver,
+        );
+        assert_eq!(result.stop, StopReason::Deadline);
+    }
+    let elapsed = start.elapsed();
+    assert!(elapsed >= budget.soft && elapsed < Duration::from_secs(2));
+
+    // Before the soft limit the search goes on, past the hard one it stops
+    let deadline = budget.deadline(Instant::now());
+    assert!(!out_of_time(&tree, &deadline));
+    let deadline = budget.deadline(Instant::now() - budget.hard);
+    assert!(out_of_time(&tree, &deadline));
+}
+
+#[test]
+fn test_seeded_search_reproducible() {
+    use michi_rust::constants::BOARDSIZE;
+    use michi_rust::mcts::{NullObserver, SearchLimits, Tree, tree_search_parallel};
+
+    let config = SearchConfig {
+        fastplay5_thres: 1.0,
+        fastplay20_thres: 1.0,
+        ..SearchConfig::default()
+    };
+    let search = |seed, threads| {
+        let mut tree = Tree::new(&Position::new());
+        tree.seed(seed);
+        let mut owner_map = vec![0i32; BOARDSIZE];
+        let result = tree_search_parallel(
+            &mut tree,
+            100,
+            threads,
+            &config,
+            &mut owner_map,
+            &SearchLimits::default(),
+            &mut NullObserver,
+        );
+        let visits: Vec<(u32, u32)> = tree
+            .children(Tree::ROOT)
+            .iter()
+            .map(|c| (c.v, c.w))
+            .collect();
+        (result.best_move, visits, owner_map)
+    };
+
+    // Threads draw from generators forked from the tree's, so even a
+    // parallel search is replayed exactly without a time limit
+    for threads in [1, 2] {
+        assert_eq!(search(42, threads), search(42, threads));
+    }
+    assert_ne!(search(42, 1).1, search(43, 1).1);
+}
+
+#[test]
+fn test_search_cancelled() {
+    use michi_rust::constants::BOARDSIZE;
+    use michi_rust::mcts::{
+        CancelToken, NullObserver, SearchLimits, StopReason, Tree, tree_search_parallel,
+    };
+    use std::time::Duration;
+
+    let config = SearchConfig {
+        fastplay5_thres: 1.0,
+        fastplay20_thres: 1.0,
+        ..SearchConfig::default()
+    };
+    let mut owner_map = vec![0i32; BOARDSIZE];
+    let cancel = CancelToken::new();
+    let limits = SearchLimits {
+        cancel: Some(cancel.clone()),
+        ..SearchLimits::default()
+    };
+
+    // Cancelled from another thread while searching
+    for threads in [1, 2] {
+        let mut tree = Tree::new(&Position::new());
+        cancel.reset();
+        let handle = cancel.clone();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            handle.cancel();
+        });
+        let result = tree_search_parallel(
+            &mut tree,
+            10_000_000,
+            threads,
+            &config,
+            &mut owner_map,
+            &limits,
+            &mut NullObserver,
+        );
+        canceller.join().unwrap();
+        assert_eq!(result.stop, StopReason::Cancelled);
+        assert!(result.sims > 0 && result.sims < 10_000_000);
+        assert!(result.best_move < BOARDSIZE);
+        assert_eq!(result.sims, tree.root().v as usize);
+    }
+
+    // A token cancelled beforehand stops the search after one simulation
+    let mut tree = Tree::new(&Position::new());
+    let result = tree_search_parallel(
+        &mut tree,
+        1000,
+        1,
+        &config,
+        &mut owner_map,
+        &limits,
+        &mut NullObserver,
+    );
+    assert_eq!(result.stop, StopReason::Cancelled);
+    assert_eq!(result.sims, 1);
+}
+
+#[test]
+fn test_tree_expand_19x19() {
+    use michi_rust::constants::MAX_N;
+    use michi_rust::mcts::{Tree, expand};
+
+    let pos = Position::with_size(MAX_N);
+    let mut tree = Tree::new(&pos);
+    expand(&mut tree, Tree::ROOT, &pos, &SearchConfig::default());
+
+    // Every point of the empty 19x19 board is a legal move
+    let children = tree.children(Tree::ROOT);
+    assert_eq!(children.len(), MAX_N * MAX_N);
+
+    // The empty-area prior discourages the first line but not the third
+    let prior = |coord: &str| {
+        let pt = parse_coord(coord);
+        let child = children
+            .iter()
+            .find(|c| c.mv() == pt)
+            .expect("child for every point");
+        child.pw as f64 / child.pv as f64
+    };
+    assert!(
+        prior("T19") < prior("R17"),
+        "first line corner should get a lower prior than the third line"
+    );
+}
+
+#[test]
+fn test_tree_search_19x19() {
+    use michi_rust::constants::MAX_N;
+    use michi_rust::mcts::{Tree, tree_search};
+
+    let mut pos = Position::with_size(MAX_N);
+    play_move(&mut pos, parse_coord("Q16")).unwrap();
+    let mut tree = Tree::new(&pos);
+
+    let result = tree_search(&mut tree, 20, &SearchConfig::default());
+    assert!(
+        pos.contains(result.best_move),
+        "Move should be on the 19x19 board"
+    );
 }
 
 // =============================================================================
@@ -549,7 +969,12 @@ fn test_mcplayout_terminates() {
     use michi_rust::playout::mcplayout;
 
     let mut pos = Position::new();
-    let _score = mcplayout(&mut pos, None);
+    let _score = mcplayout(
+        &mut pos,
+        None,
+        &SearchConfig::default(),
+        &mut fastrand::Rng::new(),
+    );
 
     // Playout should terminate (not hang)
     // The game should have progressed
@@ -561,7 +986,12 @@ fn test_mcplayout_fills_board() {
     use michi_rust::playout::mcplayout;
 
     let mut pos = Position::new();
-    let _score = mcplayout(&mut pos, None);
+    let _score = mcplayout(
+        &mut pos,
+        None,
+        &SearchConfig::default(),
+        &mut fastrand::Rng::new(),
+    );
 
     // Count empty points
     let empty_count: usize = (0..pos.color.len())
@@ -577,6 +1007,50 @@ fn test_mcplayout_fills_board() {
     );
 }
 
+#[test]
+fn test_mcplayout_19x19() {
+    use michi_rust::constants::MAX_N;
+    use michi_rust::playout::mcplayout;
+
+    let mut pos = Position::with_size(MAX_N);
+    let score = mcplayout(
+        &mut pos,
+        None,
+        &SearchConfig::default(),
+        &mut fastrand::Rng::new(),
+    );
+
+    // The playout stays within the game length limit and fills the board
+    assert!(pos.n <= pos.max_game_len());
+    assert!(score.abs() <= (MAX_N * MAX_N) as f64 + pos.komi as f64);
+    let empty_count = pos.color.iter().filter(|&&c| c == b'.').count();
+    assert!(
+        empty_count < 120,
+        "Board should be mostly filled, but {} empty points",
+        empty_count
+    );
+}
+
+#[test]
+fn test_mcplayout_seeded() {
+    use fastrand::Rng;
+    use michi_rust::playout::mcplayout;
+
+    // The same seed replays the same game
+    let playout = |seed| {
+        let mut pos = Position::new();
+        let score = mcplayout(
+            &mut pos,
+            None,
+            &SearchConfig::default(),
+            &mut Rng::with_seed(seed),
+        );
+        (score, pos.n, pos.color)
+    };
+    assert_eq!(playout(7), playout(7));
+    assert_ne!(playout(7), playout(8));
+}
+
 // =============================================================================
 // Tests inspired by michi-c test suite (requiring not-yet-implemented features)
 // =============================================================================
@@ -798,12 +1272,92 @@ fn test_large_pattern_rotations() {
 
 #[test]
 fn test_score_empty_board() {
-    // On an empty board, the score should be approximately -komi for Black
-    // (since White gets komi as compensation)
-    // But mcplayout modifies the position, so we can't test empty board directly
-    // This test is a placeholder for when we add a public score function
-    let _pos = Position::new();
-    // Score testing would require exposing the score() function from playout module
+    use michi_rust::playout::score;
+    use michi_rust::rules::Rules;
+
+    // Black to play on an empty board: only komi counts, against Black
+    for rules in [Rules::chinese(), Rules::japanese(), Rules::tromp_taylor()] {
+        let mut pos = Position::new();
+        pos.rules = rules;
+        assert_eq!(score(&pos), -7.5, "{rules:?}");
+    }
+
+    // Handicap compensation under Chinese rules
+    let mut pos = Position::new();
+    pos.handicap = 2;
+    assert_eq!(score(&pos), -9.5);
+}
+
+#[test]
+fn test_score_rules_walls() -> Result<()> {
+    use michi_rust::playout::score;
+    use michi_rust::rules::Rules;
+
+    // Black wall on column E, White wall on column F, Black to play
+    let mut moves = Vec::new();
+    for row in 1..=N {
+        moves.push(format!("E{row}"));
+        moves.push(format!("F{row}"));
+    }
+    let moves: Vec<&str> = moves.iter().map(String::as_str).collect();
+    let mut pos = setup_position(&moves)?;
+
+    // Area scoring only counts single-point eyes: stones are even
+    assert_eq!(score(&pos), -7.5);
+
+    // Tromp-Taylor counts the regions: 9 + 36 for Black, 9 + 27 for White
+    pos.rules = Rules::tromp_taylor();
+    assert_eq!(score(&pos), 45.0 - 36.0 - 7.5);
+    Ok(())
+}
+
+#[test]
+fn test_score_territory_counts_prisoners() -> Result<()> {
+    use michi_rust::playout::score;
+    use michi_rust::rules::Rules;
+
+    // Black captures White's A1 stone; White to play
+    let mut pos = setup_position(&["A2", "A1", "B1"])?;
+
+    // Area: Black has 2 stones and the A1 eye
+    assert_eq!(score(&pos), 7.5 - 3.0);
+
+    // Territory: Black has the A1 eye and one prisoner
+    pos.rules = Rules::japanese();
+    assert_eq!(score(&pos), 7.5 - 2.0);
+    Ok(())
+}
+
+#[test]
+fn test_final_score_with_dead_stones() -> Result<()> {
+    use michi_rust::playout::final_score;
+    use michi_rust::rules::Rules;
+
+    // Black wall on column E, White wall on column F, White C5 inside
+    // Black's area; Black to play
+    let mut moves = Vec::new();
+    for row in 1..=N {
+        moves.push(format!("E{row}"));
+        moves.push(format!("F{row}"));
+    }
+    moves.extend(["pass".to_string(), "C5".to_string()]);
+    let moves: Vec<&str> = moves.iter().map(String::as_str).collect();
+    let mut pos = setup_position(&moves)?;
+    let c5 = parse_coord("C5");
+
+    // With C5 on the board, A-D borders both colors and is neutral
+    assert_eq!(final_score(&pos, &[]), 9.0 - 37.0 - 7.5);
+    // Removing it gives Black 45 points against 36
+    assert_eq!(final_score(&pos, &[c5]), 45.0 - 36.0 - 7.5);
+    assert_eq!(
+        pos.color[c5], b'x',
+        "final_score does not modify the position"
+    );
+
+    // Territory: 36 + 1 prisoner against 27
+    pos.rules = Rules::japanese();
+    assert_eq!(final_score(&pos, &[c5]), 37.0 - 27.0 - 7.5);
+    Ok(())
 }
 
 // =============================================================================
@@ -812,37 +1366,72 @@ fn test_score_empty_board() {
 
 #[test]
 fn test_board_size() {
-    use michi_rust::constants::{BOARDSIZE, N};
+    use michi_rust::constants::{BOARDSIZE, MAX_N};
 
-    // Board size should be 9 or 13 depending on feature
-    assert!(
-        N == 9 || N == 13,
-        "Board size should be 9x9 or 13x13, got {}",
-        N
-    );
-    assert!(BOARDSIZE > N * N, "BOARDSIZE includes padding");
+    assert_eq!(Position::new().size, N, "Default board size");
+    const { assert!(BOARDSIZE > MAX_N * MAX_N, "BOARDSIZE includes padding") };
+
+    // Every size can be created at runtime and has exactly size^2 empty points
+    for size in [9, 13, 19] {
+        let pos = Position::with_size(size);
+        let empty = pos.color.iter().filter(|&&c| c == b'.').count();
+        assert_eq!(
+            empty,
+            size * size,
+            "{size}x{size} should have {} points",
+            size * size
+        );
+    }
 }
 
 #[test]
 fn test_board_boundaries() {
-    let pos = Position::new();
+    for size in [9, 13, 19] {
+        let pos = Position::with_size(size);
 
-    // Check that boundaries are marked as OUT (' ')
-    // First row (index 0 to N) should be OUT
-    for i in 0..=N {
-        assert_eq!(pos.color[i], b' ', "Top boundary should be OUT at {}", i);
+        // Check that boundaries are marked as OUT (' ')
+        // Bottom padding row (index 0 to W-1) should be OUT
+        for i in 0..W {
+            assert_eq!(pos.color[i], b' ', "Bottom boundary should be OUT at {}", i);
+        }
+
+        // Check left and right edges
+        for row in 1..=size {
+            assert_eq!(
+                pos.color[row * W],
+                b' ',
+                "Left boundary should be OUT at row {}",
+                row
+            );
+            assert_eq!(
+                pos.color[row * W + size + 1],
+                b' ',
+                "Right boundary should be OUT at row {}",
+                row
+            );
+        }
+
+        // Row above the top edge should be OUT
+        for col in 0..W {
+            assert_eq!(pos.color[(size + 1) * W + col], b' ');
+        }
     }
+}
 
-    // Check left edge
-    let w = N + 1; // Width including padding
-    for row in 1..=N {
+#[test]
+fn test_coords_independent_of_board_size() -> Result<()> {
+    // The same vertex maps to the same point on every board size
+    for size in [9, 13, 19] {
+        let mut pos = Position::with_size(size);
+        let d4 = parse_coord("D4");
+        play_move(&mut pos, d4)?;
+        assert_eq!(str_coord(d4), "D4");
         assert_eq!(
-            pos.color[row * w],
-            b' ',
-            "Left boundary should be OUT at row {}",
-            row
+            pos.color[d4], b'x',
+            "D4 should be occupied on {size}x{size}"
         );
     }
+    Ok(())
 }
 
 // =============================================================================
@@ -897,3 +1486,115 @@ fn test_neighbors_corner() {
         out_count
     );
 }
+
+// =============================================================================
+// GTP session tests
+// =============================================================================
+
+/// A diagnostics sink the test can read back after handing it to the engine.
+#[derive(Clone, Default)]
+struct SharedBuf(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_gtp_session_in_process() {
+    use michi_rust::gtp::GtpEngine;
+    use std::io::Cursor;
+
+    let script = "1 boardsize 9\n2 play b E5\n# a comment\n3 genmove w\n4 bogus\n5 quit\n6 name\n";
+    let log = SharedBuf::default();
+    let mut output = Vec::new();
+    let mut engine = GtpEngine::with_simulations(50)
+        .with_seed(1)
+        .with_diagnostics(log.clone());
+    engine.run_with(Cursor::new(script), &mut output).unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    let responses: Vec<&str> = output.split("\n\n").filter(|r| !r.is_empty()).collect();
+    assert_eq!(responses.len(), 5, "one response per command up to quit");
+    assert_eq!(responses[0], "=1 ");
+    assert_eq!(responses[1], "=2 ");
+    assert!(responses[2].starts_with("=3 "));
+    assert_eq!(responses[3], "?4 unknown command: bogus");
+    assert_eq!(responses[4], "=5 ");
+
+    // The board and the prompt went to the diagnostics, not the output
+    let log = String::from_utf8(log.0.lock().unwrap().clone()).unwrap();
+    assert!(log.contains("michi-rust> "));
+    assert!(!output.contains("michi-rust> "));
+
+    // Without diagnostics the responses are the same
+    let mut quiet = Vec::new();
+    GtpEngine::with_simulations(50)
+        .with_seed(1)
+        .without_diagnostics()
+        .run_with(Cursor::new(script), &mut quiet)
+        .unwrap();
+    assert_eq!(String::from_utf8(quiet).unwrap(), output);
+}
+
+#[test]
+fn test_gtp_over_tcp() {
+    use michi_rust::gtp::GtpEngine;
+    use michi_rust::server;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        // Seeded so that `genmove` below plays the same move (never a
+        // resignation, which would leave White to move) on every run
+        server::serve(listener, || {
+            GtpEngine::with_simulations(20)
+                .with_seed(1)
+                .without_diagnostics()
+        })
+    });
+
+    /// Send a command and read its response, up to the blank line.
+    fn ask(stream: &mut BufReader<TcpStream>, command: &str) -> String {
+        writeln!(stream.get_mut(), "{command}").unwrap();
+        let mut response = String::new();
+        loop {
+            let mut line = String::new();
+            assert!(
+                stream.read_line(&mut line).unwrap() > 0,
+                "connection closed"
+            );
+            if line == "\n" {
+                return response.trim_end().to_string();
+            }
+            response.push_str(&line);
+        }
+    }
+
+    let mut first = BufReader::new(TcpStream::connect(addr).unwrap());
+    let mut second = BufReader::new(TcpStream::connect(addr).unwrap());
+    assert_eq!(ask(&mut first, "1 name"), "=1 michi-rust");
+    assert_eq!(ask(&mut first, "2 play b E5"), "=2");
+    let reply = ask(&mut first, "3 genmove w");
+    assert!(reply.starts_with("=3 ") && reply != "=3 resign");
+
+    // Each connection has its own game
+    assert_eq!(ask(&mut first, "gogui-rules_side_to_move"), "= black");
+    assert_eq!(ask(&mut second, "gogui-rules_side_to_move"), "= black");
+    assert_eq!(ask(&mut second, "play b E5"), "=");
+    assert_eq!(ask(&mut first, "play b E5"), "? illegal move");
+
+    // `quit` answers, then the server closes the connection
+    assert_eq!(ask(&mut first, "quit"), "=");
+    let mut rest = String::new();
+    first.read_to_string(&mut rest).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(ask(&mut second, "gogui-rules_side_to_move"), "= white");
+}